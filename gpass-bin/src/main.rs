@@ -13,38 +13,29 @@ fn main() -> Result<()> {
 
     let args = user_args::get_args();
 
-    let mut mask : Box<dyn Mask> = match args.mask{
-        None => Box::new(masks::Standard::default()),
-        Some(mask) => env_mask!(value = &mask).ok_or_else(|| LibError::Other("Invalid Mask Value".into())).wrap_err("Mask could not be generated at runtime")?
-    }; 
+    if args.list_masks{
+        for mask in MaskRegistry::global().read().map_err(|_| LibError::Other("Mask registry unavailable".into()))?.masks(){
+            println!("{}", mask);
+        }
+        return Ok(());
+    }
+
+    //Built-in defaults < config file < environment and command line
+    let settings = config::Config::load().wrap_err("Could not read the config file")?
+        .settings(Some("gpassx"), args.profile.as_deref()).wrap_err("Could not use the config file")?; 
+    let settings = user_args::defaults().layer(settings).layer(args.settings()); 
+
+    let mut gp = GPass::default(); 
+    settings.apply(&mut gp).wrap_err("Invalid settings")?; 
+
+    if args.stdout{
+        gp.set_output_stream(std::io::stdout()); 
+    }
 
     #[cfg(feature = "colored")]
-    mask.set_color(match args.color_mask{
-        Some(ref val) => &val,
-        None => colors::DEFAULT_MASK_COLOR
-    });
-
-    let mut output_stream : Box<dyn std::io::Write> = match args.stdout{
-        false => Box::new(std::io::stderr()),
-        true => Box::new(std::io::stdout())
-    };
-
-    let gp = GPass{
-        mask, 
-        output_stream, 
-        ctrl_c_abort : !args.no_abort,
-        prompt : args.prompt, 
-
-        #[cfg(feature = "colored")]
-        prompt_color : Arc::from(match args.color_prompt{
-            Some(ref val) => val,
-            None => colors::DEFAULT_PROMPT_COLOR
-        }),    
-
-        ..Default::default()
-    };
+    gp.set_color_choice(args.color); 
     
     let password = gp.get_password().wrap_err("Failed to obtain password")?;
-    print!("{password}");
+    print!("{}", password.expose());
     Ok(())
 }
@@ -1,33 +1,128 @@
 use clap::Parser; 
+use gpass::config::Settings; 
 
 
 #[derive(Parser, Debug)]
 pub struct Args{
-    ///Mask for the password input 
+    ///Mask for the password input, ex: "standard(#)" or "or(#, color=red)", see --list-masks 
     #[clap(short, long, env = "GPASS_MASK")]
     pub mask: Option<String>, 
 
-    ///Prompt for password input 
-    #[clap(short, long, env = "GPASS_PROMPT" , default_value = "Enter Password Here: ")]
-    pub prompt : String, 
+    ///List the masks that can be given to --mask, and exit 
+    #[clap(long)]
+    pub list_masks : bool, 
+
+    ///Prompt for password input [default: "Enter Password Here: "] 
+    #[clap(short, long, env = "GPASS_PROMPT")]
+    pub prompt : Option<String>, 
 
     #[cfg(feature = "colored")]
-    ///Color for the prompt 
+    ///Color for the prompt, ex: red, "bright blue", "#ff8800", "rgb(255, 136, 0)" or none 
     #[clap(long, env = "GPASS_PROMPT_COLOR" )]
     pub color_prompt : Option<String>, 
 
     #[cfg(feature = "colored")]
-    ///Color for the mask 
+    ///Color for the mask, same values as --color-prompt 
     #[clap(long, env= "GPASS_MASK_COLOR"  )]
     pub color_mask : Option<String>,
 
+    #[cfg(feature = "colored")]
+    ///Built-in theme styling the prompt, mask and messages: default, plain, mono or vivid. --color-prompt and --color-mask override its colors 
+    #[clap(long, env = "GPASS_THEME")]
+    pub theme : Option<String>,
+
+    ///Hint shown after the prompt until something is typed 
+    #[clap(long, env = "GPASS_PLACEHOLDER")]
+    pub placeholder : Option<String>,
+
+    #[cfg(feature = "colored")]
+    ///When to use colors: auto (only on terminals, unless NO_COLOR is set), always or never 
+    #[clap(long, env = "GPASS_COLOR", default_value = "auto")]
+    pub color : gpass::colors::ColorChoice,
+
     ///Ctrl+C does not abort, instead returns password collected until then 
     #[clap(short, long, env = "GPASS_NO_ABORT")]
     pub no_abort : bool, 
 
     ///Prints to Stdout instead of Stderr 
     #[clap(short, long, env = "GPASS_STDOUT")]
-    pub stdout : bool  
+    pub stdout : bool, 
+
+    ///Ask for the password twice, both entries have to match 
+    #[clap(short, long, env = "GPASS_CONFIRM")]
+    pub confirm : bool, 
+
+    ///Prompt for the confirmation entry [default: "Confirm Password Here: "] 
+    #[clap(long, env = "GPASS_CONFIRM_PROMPT")]
+    pub confirm_prompt : Option<String>, 
+
+    ///Message shown when the entries do not match 
+    #[clap(long, env = "GPASS_MISMATCH")]
+    pub mismatch : Option<String>, 
+
+    ///Number of tries before giving up on a mismatch 
+    #[clap(long, env = "GPASS_ATTEMPTS")]
+    pub attempts : Option<usize>, 
+
+    ///Password policy, ex: "min=12,require=upper+digit,ban=password,attempts=3", or @FILE to read the rules from a file 
+    #[clap(long, env = "GPASS_POLICY")]
+    pub policy : Option<String>, 
+
+    ///Give up if the whole prompt takes longer than SECS seconds 
+    #[clap(long, env = "GPASS_TIMEOUT", value_name = "SECS")]
+    pub timeout : Option<f64>, 
+
+    ///Give up if no key is pressed for SECS seconds 
+    #[clap(long, env = "GPASS_IDLE_TIMEOUT", value_name = "SECS")]
+    pub idle_timeout : Option<f64>, 
+
+    ///What to do with pasted text: allow, strip (the trailing newline), or reject[:MESSAGE] [default: strip] 
+    #[clap(long, env = "GPASS_PASTE")]
+    pub paste : Option<String>, 
+
+    ///Profile from the config file ($GPASS_CONFIG or $XDG_CONFIG_HOME/gpass/config.toml) to use, on top of its defaults and [apps.gpassx] section 
+    #[clap(long, env = "GPASS_PROFILE")]
+    pub profile : Option<String> 
+}
+
+impl Args{
+    ///The values given on the command line or through the environment, as the top layer of settings 
+    #[allow(clippy::needless_update)]
+    pub fn settings(&self) -> Settings{
+        Settings{
+            prompt : self.prompt.clone(), 
+            mask : self.mask.clone(), 
+
+            #[cfg(feature = "colored")]
+            prompt_color : self.color_prompt.clone(), 
+            #[cfg(feature = "colored")]
+            mask_color : self.color_mask.clone(), 
+            #[cfg(feature = "colored")]
+            theme : self.theme.clone(), 
+            placeholder : self.placeholder.clone(), 
+
+            paste : self.paste.clone(), 
+            policy : self.policy.clone(), 
+            confirm : self.confirm.then_some(true), 
+            confirm_prompt : self.confirm_prompt.clone(), 
+            mismatch : self.mismatch.clone(), 
+            attempts : self.attempts, 
+            timeout : self.timeout, 
+            idle_timeout : self.idle_timeout, 
+            no_abort : self.no_abort.then_some(true), 
+
+            ..Default::default()
+        }
+    }
+}
+
+///Defaults of gpassx that differ from the library ones, the bottom layer of settings 
+pub fn defaults() -> Settings{
+    Settings{
+        prompt : Some("Enter Password Here: ".into()), 
+        confirm_prompt : Some("Confirm Password Here: ".into()), 
+        ..Default::default()
+    }
 }
 
 pub fn get_args() -> Args{
@@ -0,0 +1,247 @@
+//!Async password input for use inside a tokio runtime<br>
+//!GPass::get_password_async does the same as get_password, without blocking the executor thread<br>
+//!Dropping the future cancels the prompt, and the input (along with the terminal mode it holds) is dropped with it<br>
+//!On a terminal, a resize or `fg` redraws the prompt right away, the reactor is woken for it as it is for a key<br>
+//!Only available if the `tokio` feature flag is enabled<br>
+
+use crate::{GPass, InputToken, KeyDecoder, LibError, Secret};
+use std::future::Future;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+///Async version of the InputStream trait<br>
+///Implementations can simply write `async fn get_token(&mut self) -> ...`<br>
+pub trait AsyncInputStream {
+    ///Getting the next input token, without blocking the thread while waiting for it<br>
+    fn get_token(&mut self) -> impl Future<Output = Result<InputToken, LibError>>;
+}
+
+///Adapter decoding keystrokes from any tokio AsyncRead source (stdin, pipes, sockets)<br>
+///Bytes go through the same KeyDecoder as Getch, so escape sequences and UTF-8 are handled the same way<br>
+pub struct TokioInput<R> {
+    reader: R,
+    decoder: KeyDecoder,
+
+    ///Wakes get_token up when the prompt is to be drawn again while it waits for a key<br>
+    #[cfg(unix)]
+    redraw: Option<tty::Redraw>,
+
+    //Dropped after the reader, so stdin is back to blocking before the terminal settings are restored
+    #[cfg(unix)]
+    _terminal: Option<crate::terminal::TerminalGuard>,
+}
+
+impl<R: AsyncRead + Unpin> TokioInput<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            decoder: KeyDecoder::new(),
+
+            #[cfg(unix)]
+            redraw: None,
+            #[cfg(unix)]
+            _terminal: None,
+        }
+    }
+}
+
+impl TokioInput<Box<dyn AsyncRead + Unpin + Send>> {
+    ///Keystrokes from stdin<br>
+    ///When stdin is a terminal, it is held by a terminal::TerminalGuard and read without a blocking thread, so dropping the prompt really stops reading. The terminal is restored once the input is dropped<br>
+    ///Must be called from within a tokio runtime, a LibError::ForeignLibrary error otherwise<br>
+    ///# Panics
+    ///If the runtime was built without IO enabled (see `tokio::runtime::Builder::enable_io`) and stdin is a terminal<br>
+    pub fn stdin() -> Result<Self, LibError> {
+        tokio::runtime::Handle::try_current().map_err(|e| LibError::ForeignLibrary(Box::new(e)))?;
+
+        #[cfg(unix)]
+        if unsafe { libc::isatty(libc::STDIN_FILENO) } == 1 {
+            let terminal = crate::terminal::TerminalGuard::acquire().map_err(LibError::IOError)?;
+            let reader: Box<dyn AsyncRead + Unpin + Send> =
+                Box::new(tty::TtyReader::new().map_err(LibError::IOError)?);
+
+            return Ok(Self {
+                redraw: Some(tty::Redraw::new().map_err(LibError::IOError)?),
+                _terminal: terminal,
+                ..Self::new(reader)
+            });
+        }
+
+        Ok(Self::new(Box::new(tokio::io::stdin())))
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncInputStream for TokioInput<R> {
+    async fn get_token(&mut self) -> Result<InputToken, LibError> {
+        loop {
+            //After `fg` or a resize, whether it came before this call or while waiting for a key
+            #[cfg(unix)]
+            if crate::terminal::take_redraw() {
+                return Ok(InputToken::Redraw);
+            }
+
+            let mut byte = [0u8; 1];
+            let read = self.reader.read(&mut byte);
+
+            #[cfg(unix)]
+            let read = match self.redraw {
+                //Reads are cancel safe, so a byte is never lost to the redraw
+                Some(ref redraw) => tokio::select! {
+                    read = read => read,
+                    woken = redraw.wait() => {
+                        woken.map_err(LibError::IOError)?;
+                        continue;
+                    }
+                },
+                None => read.await,
+            };
+            #[cfg(not(unix))]
+            let read = read.await;
+
+            if read.map_err(LibError::IOError)? == 0 {
+                return Ok(InputToken::EOF);
+            }
+
+            if let Some(token) = self.decoder.push(byte[0])? {
+                return Ok(token);
+            }
+        }
+    }
+}
+
+impl GPass {
+    ///Async version of get_password, reading tokens from `input` instead of the input_stream field<br>
+    ///Timeout, confirmation and policy settings work the same as with get_password<br>
+    pub async fn get_password_async(
+        mut self,
+        mut input: impl AsyncInputStream,
+    ) -> Result<Secret, LibError> {
+        let mut session = self.start_session()?;
+        loop {
+            let token = match self.token_timeout(&session) {
+                Some(remaining) => tokio::time::timeout(remaining, input.get_token())
+                    .await
+                    .unwrap_or(Err(LibError::Timeout)),
+                None => input.get_token().await,
+            };
+
+            if let Some(password) = self.step(&mut session, token)? {
+                return Ok(password);
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+mod tty {
+    //!Non-blocking reads from a terminal on stdin, driven by the tokio reactor
+
+    use std::io::Read;
+    use std::os::unix::io::{AsRawFd, RawFd};
+    use std::pin::Pin;
+    use std::task::{ready, Context, Poll};
+    use tokio::io::unix::AsyncFd;
+    use tokio::io::{AsyncRead, ReadBuf};
+
+    struct Stdin;
+
+    impl AsRawFd for Stdin {
+        fn as_raw_fd(&self) -> RawFd {
+            libc::STDIN_FILENO
+        }
+    }
+
+    impl Read for &Stdin {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = unsafe {
+                libc::read(
+                    libc::STDIN_FILENO,
+                    buf.as_mut_ptr() as *mut libc::c_void,
+                    buf.len(),
+                )
+            };
+
+            match n {
+                n if n < 0 => Err(std::io::Error::last_os_error()),
+                n => Ok(n as usize),
+            }
+        }
+    }
+
+    ///The read end of terminal::redraw_fd, registered with the reactor<br>
+    pub(super) struct Redraw(AsyncFd<RawFd>);
+
+    impl Redraw {
+        pub(super) fn new() -> std::io::Result<Self> {
+            match crate::terminal::redraw_fd() {
+                -1 => Err(std::io::Error::other("could not open the redraw pipe")),
+                fd => Ok(Self(AsyncFd::new(fd)?)),
+            }
+        }
+
+        ///Waits until a redraw is asked for, check terminal::take_redraw afterwards<br>
+        pub(super) async fn wait(&self) -> std::io::Result<()> {
+            let mut guard = self.0.readable().await?;
+
+            let mut buf = [0u8; 64];
+            let fd = *self.0.get_ref();
+            while unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) } > 0 {}
+            guard.clear_ready();
+            Ok(())
+        }
+    }
+
+    ///Stdin switched to non-blocking mode, the original file status flags are put back on drop<br>
+    pub(super) struct TtyReader {
+        fd: AsyncFd<Stdin>,
+        flags: libc::c_int,
+    }
+
+    impl TtyReader {
+        pub(super) fn new() -> std::io::Result<Self> {
+            let flags = unsafe { libc::fcntl(libc::STDIN_FILENO, libc::F_GETFL) };
+            if flags < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+
+            if unsafe { libc::fcntl(libc::STDIN_FILENO, libc::F_SETFL, flags | libc::O_NONBLOCK) }
+                < 0
+            {
+                return Err(std::io::Error::last_os_error());
+            }
+
+            Ok(Self {
+                fd: AsyncFd::new(Stdin)?,
+                flags,
+            })
+        }
+    }
+
+    impl AsyncRead for TtyReader {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            loop {
+                let mut guard = ready!(self.fd.poll_read_ready(cx))?;
+
+                match guard.try_io(|fd| fd.get_ref().read(buf.initialize_unfilled())) {
+                    Ok(Ok(n)) => {
+                        buf.advance(n);
+                        return Poll::Ready(Ok(()));
+                    }
+                    Ok(Err(e)) => return Poll::Ready(Err(e)),
+                    Err(_would_block) => continue,
+                }
+            }
+        }
+    }
+
+    impl Drop for TtyReader {
+        fn drop(&mut self) {
+            unsafe {
+                libc::fcntl(libc::STDIN_FILENO, libc::F_SETFL, self.flags);
+            }
+        }
+    }
+}
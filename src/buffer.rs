@@ -0,0 +1,213 @@
+//!Edit buffer for the password being typed<br>
+//!Holds the password along with a cursor position, so characters can be inserted and deleted anywhere in the line<br>
+//!Masks receive the buffer on every edit, and use the cursor to redraw the right part of the line<br>
+//!The password itself lives in a zeroizing Secret, so masks never get hold of a bare String<br>
+//!Positions count grapheme clusters (what the user sees as one character), so an accented letter or an emoji with a skin tone is moved over and deleted as a whole<br>
+
+use crate::render::Glyph;
+use crate::Secret;
+use unicode_segmentation::UnicodeSegmentation;
+
+///Cursor movements supported by the edit buffer<br>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Motion {
+    ///One character to the left<br>
+    Left,
+    ///One character to the right<br>
+    Right,
+    ///Start of the password<br>
+    Home,
+    ///End of the password<br>
+    End,
+    ///Start of the current (or previous) word<br>
+    WordLeft,
+    ///End of the current (or next) word<br>
+    WordRight,
+}
+
+///The last edit applied to the buffer<br>
+///Masks like OneReverse use this to decide whether the character before the cursor is to be shown in cleartext<br>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Edit {
+    #[default]
+    None,
+    Insert,
+    ///Several characters inserted at once, none of them is to be revealed<br>
+    Paste,
+    Backspace,
+    Delete,
+    Move,
+}
+
+///Password buffer with a cursor<br>
+///The cursor is a grapheme index: 0 is before the first grapheme, `len()` is after the last one<br>
+#[derive(Default)]
+pub struct EditBuffer {
+    password: Secret,
+    cursor: usize,
+    last_edit: Edit,
+    view_width: Option<usize>,
+    placeholder: Option<Glyph>,
+}
+
+impl EditBuffer {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            password: Secret::with_capacity(capacity),
+            ..Default::default()
+        }
+    }
+
+    ///The password typed so far, in cleartext<br>
+    pub fn expose(&self) -> &str {
+        self.password.expose()
+    }
+
+    pub fn secret(&self) -> &Secret {
+        &self.password
+    }
+
+    ///Number of graphemes in the password<br>
+    pub fn len(&self) -> usize {
+        self.graphemes().count()
+    }
+
+    ///The grapheme clusters of the password, in cleartext<br>
+    pub fn graphemes(&self) -> impl Iterator<Item = &str> {
+        self.expose().graphemes(true)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.password.is_empty()
+    }
+
+    ///Cursor position as a grapheme index<br>
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn last_edit(&self) -> Edit {
+        self.last_edit
+    }
+
+    ///Number of cells the password can be drawn in, None if unlimited<br>
+    ///GPass sets it to what is left of the terminal row after the prompt, render based masks scroll the password within it (see render::fit)<br>
+    pub fn view_width(&self) -> Option<usize> {
+        self.view_width
+    }
+
+    pub fn set_view_width(&mut self, width: Option<usize>) {
+        self.view_width = width;
+    }
+
+    ///Hint drawn by render based masks while the password is empty, set by GPass from GPass::placeholder<br>
+    ///A buffer with a placeholder is expected to be drawn (with redraw_password) as soon as the prompt is printed<br>
+    pub fn placeholder(&self) -> Option<&Glyph> {
+        self.placeholder.as_ref()
+    }
+
+    pub fn set_placeholder(&mut self, placeholder: Option<Glyph>) {
+        self.placeholder = placeholder;
+    }
+
+    ///Insert a character at the cursor and move the cursor past it<br>
+    ///A combining character joins the grapheme before the cursor instead of starting a new one<br>
+    pub fn insert(&mut self, ch: char) {
+        let at = self.byte_offset(self.cursor);
+        self.password.insert(at, ch);
+        self.cursor = self.expose()[..at + ch.len_utf8()].graphemes(true).count();
+        self.last_edit = Edit::Insert;
+    }
+
+    ///Insert a whole string at the cursor and move the cursor past it<br>
+    pub fn paste(&mut self, text: &str) {
+        for ch in text.chars() {
+            self.insert(ch);
+        }
+        self.last_edit = Edit::Paste;
+    }
+
+    ///Remove the grapheme before the cursor, returns false if there was none<br>
+    pub fn backspace(&mut self) -> bool {
+        if self.cursor == 0 {
+            return false;
+        }
+
+        self.cursor -= 1;
+        self.last_edit = Edit::Backspace;
+        self.remove_grapheme();
+        true
+    }
+
+    ///Remove the grapheme under the cursor, returns false if there was none<br>
+    pub fn delete(&mut self) -> bool {
+        if self.cursor == self.len() {
+            return false;
+        }
+
+        self.last_edit = Edit::Delete;
+        self.remove_grapheme();
+        true
+    }
+
+    fn remove_grapheme(&mut self) {
+        let start = self.byte_offset(self.cursor);
+        let end = self.byte_offset(self.cursor + 1);
+        self.password.remove_range(start..end);
+    }
+
+    ///Move the cursor, returns false if the cursor did not move<br>
+    pub fn move_cursor(&mut self, motion: Motion) -> bool {
+        let password = self.password.expose();
+        let len = self.len();
+
+        //Graphemes are walked in place, collecting them would leave an unwiped copy of the password around
+        let alphanumeric = |g: &&str| g.chars().next().is_some_and(char::is_alphanumeric);
+        let word = |graphemes: &mut dyn Iterator<Item = &str>| {
+            let mut graphemes = graphemes.peekable();
+            let mut n = 0;
+            while graphemes.next_if(|g| !alphanumeric(g)).is_some() {
+                n += 1;
+            }
+            while graphemes.next_if(alphanumeric).is_some() {
+                n += 1;
+            }
+            n
+        };
+
+        let position = match motion {
+            Motion::Left => self.cursor.saturating_sub(1),
+            Motion::Right => (self.cursor + 1).min(len),
+            Motion::Home => 0,
+            Motion::End => len,
+            Motion::WordLeft => {
+                let before = &password[..self.byte_offset(self.cursor)];
+                self.cursor - word(&mut before.graphemes(true).rev())
+            }
+            Motion::WordRight => {
+                self.cursor + word(&mut password.graphemes(true).skip(self.cursor))
+            }
+        };
+
+        self.last_edit = Edit::Move;
+        let moved = position != self.cursor;
+        self.cursor = position;
+        moved
+    }
+
+    pub fn into_secret(self) -> Secret {
+        self.password
+    }
+
+    fn byte_offset(&self, index: usize) -> usize {
+        self.expose()
+            .grapheme_indices(true)
+            .nth(index)
+            .map(|(offset, _)| offset)
+            .unwrap_or(self.password.len())
+    }
+}
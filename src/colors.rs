@@ -1,6 +1,398 @@
-//!Provides a color set to both prompt and mask input<br> 
-//!By default, mask color is green and prompt color is yellow<br> 
+//!Provides a color set to both prompt and mask input<br>
+//!By default, mask color is green and prompt color is yellow<br>
+//!Colors are written as names (`red`, `bright blue`), hex (`#ff8800`, `#f80`) or RGB (`rgb(255, 136, 0)`, `255,136,0`), and `none` leaves the text plain<br>
+//!Anywhere a color is taken, a style can be given instead: `bold`, `dim`, `italic` and `underline` followed by an optional color, ex: `bold red`, `dim italic`<br>
+//!A Theme styles every part of the prompt, and comes in a few built-in flavours picked by name (`GPASS_THEME`, `gpassx --theme`)<br>
+//!Whether colors are used at all follows `NO_COLOR`, `CLICOLOR_FORCE` and whether the output stream is a terminal, see ColorChoice<br>
 //! Functionality only available by enabling feature flag `colored`<br>
 
-pub const DEFAULT_MASK_COLOR: &'static str = "green";
-pub const DEFAULT_PROMPT_COLOR: &'static str = "yellow";
+use colored::Color;
+use std::str::FromStr;
+
+pub const DEFAULT_MASK_COLOR: &str = "green";
+pub const DEFAULT_PROMPT_COLOR: &str = "yellow";
+///Style of the character OneReverse and MimiReverse show in cleartext<br>
+pub const DEFAULT_REVEAL_STYLE: &str = "bold green";
+
+///Color value for plain, uncolored text<br>
+pub const NONE: &str = "none";
+
+///Parses a color value, None for `none`<br>
+pub fn parse(color: &str) -> Result<Option<Color>, String> {
+    let trimmed = color.trim();
+    let lower = trimmed.to_ascii_lowercase();
+
+    if lower == NONE {
+        return Ok(None);
+    }
+
+    if let Some(hex) = lower.strip_prefix('#') {
+        return parse_hex(hex)
+            .map(Some)
+            .ok_or_else(|| format!("invalid hex color {:?}", trimmed));
+    }
+
+    let rgb = lower
+        .strip_prefix("rgb(")
+        .and_then(|rgb| rgb.strip_suffix(')'))
+        .or_else(|| lower.contains(',').then_some(lower.as_str()));
+    if let Some(rgb) = rgb {
+        return parse_rgb(rgb)
+            .map(Some)
+            .ok_or_else(|| format!("invalid RGB color {:?}", trimmed));
+    }
+
+    lower
+        .replace(['_', '-'], " ")
+        .parse()
+        .map(Some)
+        .map_err(|_| format!("unknown color {:?}", trimmed))
+}
+
+fn parse_hex(hex: &str) -> Option<Color> {
+    if !hex.chars().all(|ch| ch.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let channel = |s: &str| u8::from_str_radix(s, 16).ok();
+    match hex.len() {
+        3 => {
+            let short = |i: usize| channel(&hex[i..i + 1]).map(|c| c * 17);
+            Some(Color::TrueColor {
+                r: short(0)?,
+                g: short(1)?,
+                b: short(2)?,
+            })
+        }
+        6 => Some(Color::TrueColor {
+            r: channel(&hex[0..2])?,
+            g: channel(&hex[2..4])?,
+            b: channel(&hex[4..6])?,
+        }),
+        _ => None,
+    }
+}
+
+fn parse_rgb(rgb: &str) -> Option<Color> {
+    let channels = rgb
+        .split(',')
+        .map(|c| c.trim().parse::<u8>().ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    match channels[..] {
+        [r, g, b] => Some(Color::TrueColor { r, g, b }),
+        _ => None,
+    }
+}
+
+///Whether the terminal takes 24 bit colors, going by `COLORTERM`<br>
+///Hex and RGB colors fall back to the closest of the 16 basic colors when it does not<br>
+pub fn truecolor() -> bool {
+    matches!(
+        std::env::var("COLORTERM").as_deref(),
+        Ok("truecolor") | Ok("24bit")
+    )
+}
+
+///The basic colors with the RGB values of the xterm palette<br>
+const BASIC: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::White, (229, 229, 229)),
+    (Color::BrightBlack, (127, 127, 127)),
+    (Color::BrightRed, (255, 0, 0)),
+    (Color::BrightGreen, (0, 255, 0)),
+    (Color::BrightYellow, (255, 255, 0)),
+    (Color::BrightBlue, (92, 92, 255)),
+    (Color::BrightMagenta, (255, 0, 255)),
+    (Color::BrightCyan, (0, 255, 255)),
+    (Color::BrightWhite, (255, 255, 255)),
+];
+
+///The basic color closest to a 24 bit color<br>
+fn closest_basic(r: u8, g: u8, b: u8) -> Color {
+    let distance = |(br, bg, bb): (u8, u8, u8)| {
+        let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2);
+        d(r, br) + d(g, bg) + d(b, bb)
+    };
+
+    BASIC
+        .iter()
+        .min_by_key(|(_, rgb)| distance(*rgb))
+        .map(|(color, _)| *color)
+        .unwrap_or(Color::White)
+}
+
+///A color with text attributes, parsed from values like `bold red` or `dim`<br>
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    pub color: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl Style {
+    ///The SGR parameters for the style, empty for plain text<br>
+    fn codes(&self) -> Vec<String> {
+        let mut codes: Vec<String> = [
+            (self.bold, "1"),
+            (self.dim, "2"),
+            (self.italic, "3"),
+            (self.underline, "4"),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, code)| code.to_string())
+        .collect();
+
+        match self.color {
+            Some(Color::TrueColor { r, g, b }) if !truecolor() => {
+                codes.push(closest_basic(r, g, b).to_fg_str().into_owned())
+            }
+            Some(color) => codes.push(color.to_fg_str().into_owned()),
+            None => {}
+        }
+        codes
+    }
+}
+
+///Attribute words first, then the color (which may itself be several words, ex: `bold bright blue`)<br>
+impl FromStr for Style {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut style = Style::default();
+        let mut words = s.split_whitespace().peekable();
+
+        while let Some(word) = words.peek() {
+            let flag = match word.to_ascii_lowercase().as_str() {
+                "bold" => &mut style.bold,
+                "dim" => &mut style.dim,
+                "italic" => &mut style.italic,
+                "underline" => &mut style.underline,
+                _ => break,
+            };
+            *flag = true;
+            words.next();
+        }
+
+        let color = words.collect::<Vec<_>>().join(" ");
+        if !color.is_empty() {
+            style.color = parse(&color)?;
+        }
+        Ok(style)
+    }
+}
+
+///Wraps `text` in the escape codes for `style` (a color or a style, see Style), plain for `none` or a value that does not parse<br>
+///The codes are written as asked, whether to color at all is decided beforehand (see ColorChoice)<br>
+pub fn paint(text: &str, style: &str) -> String {
+    let codes = style
+        .parse::<Style>()
+        .map(|style| style.codes())
+        .unwrap_or_default();
+
+    if codes.is_empty() {
+        return text.to_string();
+    }
+
+    //Sized up front, the text may be a password character that must not be left behind in a buffer given up while growing
+    let codes = codes.join(";");
+    let mut painted = String::with_capacity(codes.len() + text.len() + 7);
+    painted.push_str("\x1b[");
+    painted.push_str(&codes);
+    painted.push('m');
+    painted.push_str(text);
+    painted.push_str("\x1b[0m");
+    painted
+}
+
+///Reads a color (or style) from the environment variable `var`, `default` if it is not set<br>
+///An invalid value is an error rather than falling back silently<br>
+pub fn color_from_env(var: &str, default: &str) -> Result<String, String> {
+    match std::env::var(var) {
+        Ok(color) => match color.parse::<Style>() {
+            Ok(_) => Ok(color.trim().to_string()),
+            Err(e) => Err(format!("{}: {}", var, e)),
+        },
+        Err(_) => Ok(default.to_string()),
+    }
+}
+
+///The mask color from `GPASS_MASK_COLOR`, DEFAULT_MASK_COLOR if it is not set<br>
+pub fn mask_color_from_env() -> Result<String, String> {
+    color_from_env("GPASS_MASK_COLOR", DEFAULT_MASK_COLOR)
+}
+
+///The prompt color from `GPASS_PROMPT_COLOR`, DEFAULT_PROMPT_COLOR if it is not set<br>
+pub fn prompt_color_from_env() -> Result<String, String> {
+    color_from_env("GPASS_PROMPT_COLOR", DEFAULT_PROMPT_COLOR)
+}
+
+///When to color the prompt and mask<br>
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    ///Color unless `NO_COLOR` is set, `TERM` is `dumb` or the output is not a terminal. `CLICOLOR_FORCE` colors regardless<br>
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    ///Whether to color output going to a stream that is (or is not) a terminal<br>
+    pub fn enabled(self, is_terminal: bool) -> bool {
+        let set = |var: &str| std::env::var_os(var).filter(|value| !value.is_empty());
+
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto if set("NO_COLOR").is_some() => false,
+            ColorChoice::Auto if set("CLICOLOR_FORCE").is_some_and(|value| value != "0") => true,
+            ColorChoice::Auto if std::env::var_os("TERM").is_some_and(|term| term == "dumb") => {
+                false
+            }
+            ColorChoice::Auto => is_terminal,
+        }
+    }
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!("{:?} is not one of auto, always, never", s)),
+        }
+    }
+}
+
+///Styles for every part of the prompt, each one a color or style value (see Style)<br>
+///Masks take the ones that concern them through Mask::set_theme, GPass uses the rest<br>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    pub prompt: String,
+    pub mask: String,
+    ///The character shown in cleartext by OneReverse and MimiReverse<br>
+    pub reveal: String,
+    ///Hint shown while nothing has been typed, see GPass::set_placeholder<br>
+    pub placeholder: String,
+    ///Why a password does not pass the policy, and rejected pastes<br>
+    pub error: String,
+    ///The message printed when the confirmation does not match<br>
+    pub mismatch: String,
+    ///Strength meter bars, weak to strong<br>
+    pub weak: String,
+    pub fair: String,
+    pub strong: String,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::new([
+            DEFAULT_PROMPT_COLOR,
+            DEFAULT_MASK_COLOR,
+            DEFAULT_REVEAL_STYLE,
+            "dim",
+            "red",
+            "red",
+            "red",
+            "yellow",
+            "green",
+        ])
+    }
+}
+
+impl Theme {
+    ///A theme from its styles, in the order of the fields<br>
+    fn new(styles: [&str; 9]) -> Self {
+        let [prompt, mask, reveal, placeholder, error, mismatch, weak, fair, strong] =
+            styles.map(String::from);
+
+        Self {
+            prompt,
+            mask,
+            reveal,
+            placeholder,
+            error,
+            mismatch,
+            weak,
+            fair,
+            strong,
+        }
+    }
+
+    ///Names of the built-in themes<br>
+    pub const NAMES: &'static [&'static str] = &["default", "plain", "mono", "vivid"];
+
+    ///A built-in theme by name (case insensitive)<br>
+    ///`default` is the yellow prompt and green mask, `plain` has no styles at all, `mono` only uses bold, dim and italic, and `vivid` uses bright colors<br>
+    pub fn named(name: &str) -> Option<Theme> {
+        let theme = match name.trim().to_ascii_lowercase().as_str() {
+            "default" => Theme::default(),
+            "plain" => Theme::plain(),
+            "mono" => Theme::new([
+                "bold",
+                NONE,
+                "bold",
+                "dim italic",
+                "bold",
+                "bold",
+                "dim",
+                NONE,
+                "bold",
+            ]),
+            "vivid" => Theme::new([
+                "bold bright cyan",
+                "bright magenta",
+                "bold bright white",
+                "italic bright black",
+                "bold bright red",
+                "bold bright yellow",
+                "bright red",
+                "bright yellow",
+                "bright green",
+            ]),
+            _ => return None,
+        };
+        Some(theme)
+    }
+
+    ///No colors or attributes anywhere<br>
+    pub fn plain() -> Self {
+        Self::new([NONE; 9])
+    }
+
+    ///The built-in theme named by `GPASS_THEME`, None if it is not set<br>
+    pub fn from_env() -> Result<Option<Theme>, String> {
+        match std::env::var("GPASS_THEME") {
+            Ok(name) => name.parse().map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+///A built-in theme by name, see Theme::named<br>
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Theme::named(s).ok_or_else(|| {
+            format!(
+                "unknown theme {:?}, expected one of {}",
+                s,
+                Theme::NAMES.join(", ")
+            )
+        })
+    }
+}
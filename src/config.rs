@@ -0,0 +1,296 @@
+//!Layered configuration for GPass, from a TOML file and the environment<br>
+//!The file lives at `$GPASS_CONFIG`, or `$XDG_CONFIG_HOME/gpass/config.toml` (`~/.config/gpass/config.toml` if XDG_CONFIG_HOME is not set)<br>
+//!Top level keys are the defaults, `[apps.NAME]` sections apply to one application and `[profiles.NAME]` sections are picked by name (ex: `gpassx --profile work`)<br>
+//!Values are layered, each layer overriding the ones before it: built-in defaults < file defaults < app section < profile < environment < values set through the API or the command line<br>
+//!The module is only available if the `config` feature flag is enabled<br>
+//!```toml
+//!prompt = "Password: "
+//!mask = "or(#)"
+//!
+//![apps.backup]
+//!prompt = "Backup key: "
+//!
+//![profiles.paranoid]
+//!mask = "blind"
+//!paste = "reject"
+//!confirm = true
+//!```
+
+#[cfg(feature = "colored")]
+use crate::colors::Style;
+use crate::policy::Policy;
+use crate::{GPass, LibError, MaskRegistry};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+///One layer of settings, every value is optional so layers only override what they set<br>
+///Masks are mask specs (see the spec module), colors are colors or styles and themes are built-in theme names (see the colors module), policies are policy specs or `@FILE`, timeouts are in seconds<br>
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub prompt: Option<String>,
+    pub mask: Option<String>,
+    pub prompt_color: Option<String>,
+    pub mask_color: Option<String>,
+    pub theme: Option<String>,
+    pub placeholder: Option<String>,
+    pub paste: Option<String>,
+    pub policy: Option<String>,
+    pub confirm: Option<bool>,
+    pub confirm_prompt: Option<String>,
+    pub mismatch: Option<String>,
+    pub attempts: Option<usize>,
+    pub timeout: Option<f64>,
+    pub idle_timeout: Option<f64>,
+    pub no_abort: Option<bool>,
+}
+
+impl Settings {
+    ///These settings, overridden by whatever `over` sets<br>
+    pub fn layer(self, over: Settings) -> Settings {
+        Settings {
+            prompt: over.prompt.or(self.prompt),
+            mask: over.mask.or(self.mask),
+            prompt_color: over.prompt_color.or(self.prompt_color),
+            mask_color: over.mask_color.or(self.mask_color),
+            theme: over.theme.or(self.theme),
+            placeholder: over.placeholder.or(self.placeholder),
+            paste: over.paste.or(self.paste),
+            policy: over.policy.or(self.policy),
+            confirm: over.confirm.or(self.confirm),
+            confirm_prompt: over.confirm_prompt.or(self.confirm_prompt),
+            mismatch: over.mismatch.or(self.mismatch),
+            attempts: over.attempts.or(self.attempts),
+            timeout: over.timeout.or(self.timeout),
+            idle_timeout: over.idle_timeout.or(self.idle_timeout),
+            no_abort: over.no_abort.or(self.no_abort),
+        }
+    }
+
+    ///Settings from the `GPASS_*` environment variables (the same ones gpassx reads), ex: GPASS_PROMPT, GPASS_MASK, GPASS_IDLE_TIMEOUT<br>
+    pub fn from_env() -> Result<Settings, LibError> {
+        Ok(Settings {
+            prompt: var("GPASS_PROMPT"),
+            mask: var("GPASS_MASK"),
+            prompt_color: var("GPASS_PROMPT_COLOR"),
+            mask_color: var("GPASS_MASK_COLOR"),
+            theme: var("GPASS_THEME"),
+            placeholder: var("GPASS_PLACEHOLDER"),
+            paste: var("GPASS_PASTE"),
+            policy: var("GPASS_POLICY"),
+            confirm: parsed_var("GPASS_CONFIRM")?,
+            confirm_prompt: var("GPASS_CONFIRM_PROMPT"),
+            mismatch: var("GPASS_MISMATCH"),
+            attempts: parsed_var("GPASS_ATTEMPTS")?,
+            timeout: parsed_var("GPASS_TIMEOUT")?,
+            idle_timeout: parsed_var("GPASS_IDLE_TIMEOUT")?,
+            no_abort: parsed_var("GPASS_NO_ABORT")?,
+        })
+    }
+
+    ///Sets up `gpass` with every value present, values left out keep what `gpass` already has<br>
+    pub fn apply(&self, gpass: &mut GPass) -> Result<(), LibError> {
+        if let Some(ref prompt) = self.prompt {
+            gpass.set_prompt(prompt);
+        }
+
+        if let Some(ref mask) = self.mask {
+            gpass.mask = MaskRegistry::resolve_global(mask)?;
+        }
+
+        //The theme styles the mask too, so it goes after the mask and before the colors
+        #[cfg(feature = "colored")]
+        if let Some(ref theme) = self.theme {
+            gpass.set_theme(theme.parse().map_err(invalid("theme"))?);
+        }
+
+        #[cfg(feature = "colored")]
+        if let Some(ref color) = self.mask_color {
+            color.parse::<Style>().map_err(invalid("mask_color"))?;
+            gpass.set_mask_color(color);
+        }
+
+        #[cfg(feature = "colored")]
+        if let Some(ref color) = self.prompt_color {
+            color.parse::<Style>().map_err(invalid("prompt_color"))?;
+            gpass.set_prompt_color(color);
+        }
+
+        if let Some(ref placeholder) = self.placeholder {
+            gpass.set_placeholder(placeholder);
+        }
+
+        if let Some(ref paste) = self.paste {
+            gpass.paste = paste.parse().map_err(invalid("paste"))?;
+        }
+
+        if let Some(ref policy) = self.policy {
+            let policy = match policy.strip_prefix('@') {
+                Some(path) => {
+                    let path = Path::new(path);
+                    let contents = std::fs::read_to_string(path).map_err(|e| {
+                        let message = format!("could not read the policy file {}", path.display());
+                        LibError::config_caused(Some(path), message, e)
+                    })?;
+                    Policy::from_lines(&contents)
+                }
+                None => policy.parse(),
+            };
+            gpass.policy = Some(policy.map_err(invalid("policy"))?);
+        }
+
+        let confirm = self.confirm.unwrap_or(gpass.confirm.is_some());
+        if confirm {
+            let mut settings = gpass.confirm.take().unwrap_or_default();
+            if let Some(ref prompt) = self.confirm_prompt {
+                settings.prompt = prompt.clone();
+            }
+            if let Some(ref mismatch) = self.mismatch {
+                settings.mismatch = mismatch.clone();
+            }
+            if let Some(attempts) = self.attempts {
+                settings.attempts = attempts;
+            }
+            gpass.confirm = Some(settings);
+        } else {
+            gpass.confirm = None;
+        }
+
+        if self.timeout.is_some() || self.idle_timeout.is_some() {
+            let mut timeout = gpass.timeout.unwrap_or_default();
+            if let Some(total) = self.timeout {
+                timeout.total = Some(seconds(total, "timeout")?);
+            }
+            if let Some(idle) = self.idle_timeout {
+                timeout.idle = Some(seconds(idle, "idle_timeout")?);
+            }
+            gpass.timeout = Some(timeout);
+        }
+
+        if let Some(no_abort) = self.no_abort {
+            gpass.ctrl_c_abort = !no_abort;
+        }
+
+        Ok(())
+    }
+}
+
+fn var(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+fn parsed_var<T>(name: &str) -> Result<Option<T>, LibError>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    match var(name) {
+        Some(value) => value.trim().parse().map(Some).map_err(|e| {
+            LibError::config_caused(None, format!("invalid value {:?} for {}", value, name), e)
+        }),
+        None => Ok(None),
+    }
+}
+
+fn invalid<E: std::fmt::Display>(key: &'static str) -> impl Fn(E) -> LibError {
+    move |e| LibError::config(format!("invalid {}: {}", key, e))
+}
+
+fn seconds(secs: f64, key: &str) -> Result<Duration, LibError> {
+    Duration::try_from_secs_f64(secs)
+        .map_err(|e| LibError::config_caused(None, format!("invalid {}: {} seconds", key, secs), e))
+}
+
+///Contents of a config file<br>
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize)]
+pub struct Config {
+    ///Top level keys<br>
+    #[serde(flatten)]
+    pub defaults: Settings,
+
+    ///`[apps.NAME]` sections<br>
+    #[serde(default)]
+    pub apps: HashMap<String, Settings>,
+
+    ///`[profiles.NAME]` sections<br>
+    #[serde(default)]
+    pub profiles: HashMap<String, Settings>,
+}
+
+impl Config {
+    ///Where the config file is looked for, None if no location can be worked out<br>
+    pub fn path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os("GPASS_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+
+        let base = match std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(base.join("gpass").join("config.toml"))
+    }
+
+    ///The config file at Config::path, an empty config if there is none<br>
+    pub fn load() -> Result<Config, LibError> {
+        match Self::path() {
+            Some(path) if path.exists() => Self::from_file(path),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    ///The config file at `path`, errors keep the path and the io::Error or toml::de::Error behind them<br>
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Config, LibError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            LibError::config_caused(Some(path), format!("could not read {}", path.display()), e)
+        })?;
+        toml::from_str(&contents).map_err(|e| {
+            LibError::config_caused(Some(path), format!("could not parse {}", path.display()), e)
+        })
+    }
+
+    ///The file layers for `app` and `profile`: defaults < app section < profile<br>
+    ///A missing app section is fine, a missing profile is an error<br>
+    pub fn settings(&self, app: Option<&str>, profile: Option<&str>) -> Result<Settings, LibError> {
+        let mut settings = self.defaults.clone();
+
+        if let Some(section) = app.and_then(|app| self.apps.get(app)) {
+            settings = settings.layer(section.clone());
+        }
+
+        if let Some(profile) = profile {
+            match self.profiles.get(profile) {
+                Some(section) => settings = settings.layer(section.clone()),
+                None => return Err(LibError::config(format!("unknown profile {:?}", profile))),
+            }
+        }
+
+        Ok(settings)
+    }
+}
+
+impl FromStr for Config {
+    type Err = LibError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s)
+            .map_err(|e| LibError::config_caused(None, "could not parse the config", e))
+    }
+}
+
+impl GPass {
+    ///A GPass set up from the config file and the environment, for the application `app` and the profile `profile`<br>
+    ///Fields set on the result afterwards take precedence over both, being the explicit layer<br>
+    pub fn from_config(app: Option<&str>, profile: Option<&str>) -> Result<Self, LibError> {
+        let settings = Config::load()?
+            .settings(app, profile)?
+            .layer(Settings::from_env()?);
+
+        let mut gpass = GPass::default();
+        settings.apply(&mut gpass)?;
+        Ok(gpass)
+    }
+}
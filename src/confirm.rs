@@ -0,0 +1,39 @@
+//!Confirmation mode: the password is asked for twice and both entries have to match<br>
+//!Used when setting a new credential, see GPass::set_confirm<br>
+
+///Settings for the second (confirmation) prompt<br>
+#[derive(Debug, Clone)]
+pub struct Confirm {
+    ///Prompt for the second entry<br>
+    pub prompt: String,
+
+    ///Message shown when the two entries do not match<br>
+    pub mismatch: String,
+
+    ///Number of tries before giving up with LibError::ConfirmationFailed<br>
+    pub attempts: usize,
+}
+
+pub const DEFAULT_CONFIRM_PROMPT: &str = "Confirm the Password";
+pub const DEFAULT_MISMATCH_MESSAGE: &str = "Passwords do not match, try again";
+pub const DEFAULT_CONFIRM_ATTEMPTS: usize = 3;
+
+impl Default for Confirm {
+    fn default() -> Self {
+        Self {
+            prompt: DEFAULT_CONFIRM_PROMPT.into(),
+            mismatch: DEFAULT_MISMATCH_MESSAGE.into(),
+            attempts: DEFAULT_CONFIRM_ATTEMPTS,
+        }
+    }
+}
+
+impl Confirm {
+    pub fn new(prompt: Option<&str>, mismatch: Option<&str>, attempts: usize) -> Self {
+        Self {
+            prompt: prompt.unwrap_or(DEFAULT_CONFIRM_PROMPT).to_string(),
+            mismatch: mismatch.unwrap_or(DEFAULT_MISMATCH_MESSAGE).to_string(),
+            attempts,
+        }
+    }
+}
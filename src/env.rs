@@ -1,11 +1,10 @@
 //!A set of macros to access prompt and mask values from the environment variables <br>
 //!This is to be used with the optional feature flag `env`<br>
 //!Provided macros -> env_prompt, env_mask, mask_env_color, prompt_env_color <br>
-//!Note, even though env_color! has been exported, it is not supposed to be used by itself. Use the mask_env_color and prompt_env_color macros instead<br>
+//!Note, even though env_color! has been exported, it is not supposed to be used by itself. Use the mask_env_color and prompt_env_color macros instead (or the functions in colors)<br>
 
-use crate::Mask;
-use regex::Regex;
-use std::sync::RwLock;
+use crate::spec::SpecError;
+use crate::{Mask, MaskRegistry};
 
 ///Get prompt from the environment if available, else default to default_prompt<br>
 ///Inner function for the macro env_prompt!<br>
@@ -16,13 +15,15 @@ pub fn prompt_from_env(env_var: &str, default_prompt: &str) -> String {
     }
 }
 
-const GPASS_DEFAULT_PROMPT: &'static str = "Enter Password Here: ";
-const GPASS_DEFAULT_ENV_VAR: &'static str = "GPASS_PROMPT";
+#[doc(hidden)]
+pub const GPASS_DEFAULT_PROMPT: &str = "Enter Password Here: ";
+#[doc(hidden)]
+pub const GPASS_DEFAULT_ENV_VAR: &str = "GPASS_PROMPT";
 
 #[macro_export]
 ///Used to get the password prompt eg. "Enter Password Here: " from the environment variables <br>
 ///By default the `GPASS_PROMPT` environment variable is evaluated, and if it is not set, it returns default "Enter Password Here: " <br>
-
+///
 ///This macro can be used 4 ways <br>
 /// env_prompt!() -> Uses `GPASS_PROMPT` env key and defaults to "Enter Password Here: "<br>
 /// env_prompt!("MY_ENV_VAR") : Customize the environment variable <br>
@@ -30,7 +31,13 @@ const GPASS_DEFAULT_ENV_VAR: &'static str = "GPASS_PROMPT";
 /// env_prompt!("MY_ENV_VAR" , "My Prompt" ) : Customize both <br>
 macro_rules! env_prompt {
     () => {
-        $crate::env::prompt_from_env(GPASS_DEFAULT_ENV_VAR, GPASS_DEFAULT_PROMPT)
+        $crate::env::prompt_from_env($crate::env::GPASS_DEFAULT_ENV_VAR, $crate::env::GPASS_DEFAULT_PROMPT)
+    };
+
+    //Add identifier so we can use prompt_from_env!(default = "default")
+    //Has to come before the expr arms, which would take `default = ..` for an assignment
+    (default = $default : expr) => {
+        $crate::env::prompt_from_env($crate::env::GPASS_DEFAULT_ENV_VAR, $default)
     };
 
     ($prompt : expr , $default : expr) => {
@@ -38,209 +45,81 @@ macro_rules! env_prompt {
     };
 
     ($prompt : expr ) => {
-        $crate::env::prompt_from_env($prompt, GPASS_DEFAULT_PROMPT)
-    };
-
-    //Add identifier so we can use prompt_from_env!(default = "default")
-    (default = $default : expr) => {
-        $crate::env::prompt_from_env(GPASS_DEFAULT_ENV_VAR, $default)
+        $crate::env::prompt_from_env($prompt, $crate::env::GPASS_DEFAULT_PROMPT)
     };
 }
 
-///Get mask from the environment if available<br>
+///Get mask from the environment if available, None if the variable is not set<br>
 ///Inner function for the macro env_mask!<br>
 pub fn mask_from_env(
     env_var: &str,
-    converters: &[fn(&str) -> Option<Box<dyn Mask>>],
-) -> Option<Box<dyn Mask>> {
+    registry: Option<&MaskRegistry>,
+) -> Option<Result<Box<dyn Mask>, SpecError>> {
     let mask = std::env::var(env_var);
     match mask {
-        Ok(val) => mask_from_str(&val, Some(converters)),
+        Ok(val) => Some(mask_from_str(&val, registry)),
         Err(_) => None,
     }
 }
 
+///Parse a mask spec (see the spec module) and build the mask registered under its name<br>
+///Uses the global registry (MaskRegistry::global) if no registry is given<br>
 pub fn mask_from_str(
     val: &str,
-    mut converters: Option<&[fn(&str) -> Option<Box<dyn Mask>>]>,
-) -> Option<Box<dyn Mask>> {
-    if converters.is_none() {
-        converters = Some(&*DEFAULT_CONVERTERS);
+    registry: Option<&MaskRegistry>,
+) -> Result<Box<dyn Mask>, SpecError> {
+    match registry {
+        Some(registry) => registry.resolve(val),
+        None => MaskRegistry::resolve_global(val),
     }
-
-    for converter in converters.unwrap().iter() {
-        if let Some(mask) = converter(&val) {
-            return Some(mask);
-        }
-    }
-
-    None
-}
-
-///Helper function for regex based mask obtains<br>
-///Given a base_string(say "standard"), it matches against standard -> returns Standard::default()<br>
-///It also matches against Standard(<mask_string>) and Standard[<mask_string>] and Standard{<mask_string>} to return Standard{mask : <mask_string>}<br>
-/// These matches are case insensitive<br>
-pub fn mask_string_get(base_string: &str, check_string: &str) -> Option<String> {
-    let re = format!(r"(?i){base_string}");
-
-    if Regex::new(re.as_str()).unwrap().is_match(check_string) {
-        return Some(crate::masks::DEFAULT_MASK_CHAR.to_string());
-    }
-
-    let re1 = format!(r"(?i){base_string}\((?P<mask_string>.*)\)");
-    let re2 = format!(r"(?i){base_string}\[(?P<mask_string>.*)\]");
-    let re3 = format!(r"(?i){base_string}\{{(?P<mask_string>.*)\}}");
-
-    //?i sets up case insensitive
-
-    for regular_expression in [re1, re2, re3].iter() {
-        let re = Regex::new(regular_expression.as_str()).unwrap();
-        if let Some(captures) = re.captures(check_string) {
-            return match captures.name("mask_string") {
-                Some(ref t) => Some(t.to_owned().as_str().to_string()),
-                None => None,
-            };
-        }
-    }
-
-    None
-}
-
-lazy_static::lazy_static! {
-
-    #[allow(unused_parens)]
-
-    ///Default Converter Functions<br>
-    ///Each converter function uses the signature fn(&str) -> Option<Box<dyn Mask>><br>
-    ///A converter is supposed to parse the string input for whatever pattern matches that mask <br>
-    /// Ex: "blind" will be accepted only by the Converter for the Blind mask, none else <br>
-    /// A converter shows success by returning Some(variant) and failure by returning None variant <br>
-    /// You can also provide your own converters for custom masks <br>
-    pub static ref DEFAULT_CONVERTERS : Vec<fn(&str) -> Option<Box<dyn Mask>>> = {
-
-        let converters : Vec<fn(&str) -> Option<Box<dyn Mask>>> = vec![
-
-            |s : &str| {
-                if s.trim().to_lowercase() == "blind" {
-                    Some(Box::new(crate::masks::Blind{}))
-                }
-                else {None}
-            },
-            |s : &str| {
-                if s.trim().to_lowercase() == "echo" {
-                    Some(Box::new(crate::masks::Echo::default()))
-                }
-                else {None}
-            },
-
-            |s : &str| {
-
-                let s = s.trim();
-                match mask_string_get("standard" , s){
-                    Some(res) => Some(Box::new(crate::masks::Standard::new(&res))),
-                    None => None
-                }
-            },
-
-            #[cfg(feature = "reverse")]
-            |s : &str| {
-
-                let s = s.trim();
-                match mask_string_get("onereverse" , s){
-                    Some(res) => return Some(Box::new(crate::reverse::OneReverse::new(&res))),
-                    None => {}
-                };
-
-                match mask_string_get("or" , s){
-                    Some(res) => return Some(Box::new(crate::reverse::OneReverse::new(&res))),
-                    None => None
-                }
-            },
-
-            #[cfg(feature = "reverse")]
-            |s : &str| {
-                let s = s.trim();
-                match mask_string_get("mimi" , s){
-                    Some(res) => return Some(Box::new(crate::reverse::MimiReverse::new(&res))),
-                    None => {}
-                };
-
-                match mask_string_get("mimireverse" ,s) {
-                    Some(res) => return Some(Box::new(crate::reverse::MimiReverse::new(&res))),
-                    None => {}
-                };
-
-                match mask_string_get("mr" , s){
-                    Some(res) => return Some(Box::new(crate::reverse::MimiReverse::new(&res))),
-                    None => None
-                }
-            }
-
-        ];
-
-        converters
-    };
-
 }
 
 #[macro_export]
 ///Macro to get a mask from the environment <br>
 ///By default uses the environment variable `GPASS_MASK`<br>
+///Mask names are looked up in MaskRegistry::global, so masks registered there by the application work as well<br>
+///Invalid mask values come back as a spec::SpecError, with the position of the mistake<br>
 ///Examples of usage:<br>
-/// 1. `env_mask!()` -> Returns from environment variable `GPASS_MASK`<br>
+/// 1. `env_mask!()` -> Returns from environment variable `GPASS_MASK`, None if it is not set<br>
 /// 2. `env_mask!("MY_ENV_VAR")` -> Returns from custom env variable<br>
-/// 3. `env_mask!(default = masks::Standard::default()) -> Provide a default in case of fallback <br>
-/// 4. `env_mask!("MY_ENV_VAR" , masks::Standard::default()) -> Provide a default and a custom environment variable<br>
-/// 5. env_mask!(value = "MY_MASK_VALUE") -> For already parsed environments, where the value represents value of the mask(say "standard(**)")<br>
+/// 3. `env_mask!(default = masks::Standard::default())` -> Provide a default in case the variable is not set <br>
+/// 4. `env_mask!("MY_ENV_VAR" , default = masks::Standard::default())` -> Provide a default and a custom environment variable<br>
+/// 5. `env_mask!(value = "MY_MASK_VALUE")` -> For already parsed environments, where the value represents value of the mask(say "standard(**)")<br>
 macro_rules! env_mask {
     (value = $val : expr) => {
-        mask_from_str($val, &*DEFAULT_CONVERTERS)
+        $crate::env::mask_from_str($val, None)
     };
 
     () => {
-        mask_from_env("GPASS_MASK", &*DEFAULT_CONVERTERS)
+        $crate::env::mask_from_env("GPASS_MASK", None)
     };
 
-    ($mask : expr) => {
-        mask_from_env($mask, &*DEFAULT_CONVERTERS)
+    //The default forms have to come before the expr arms, which would take `default = ..` for an assignment
+    (default = $default : expr) => {
+        match $crate::env::mask_from_env("GPASS_MASK", None) {
+            Some(mask) => mask,
+            None => Ok(Box::new($default) as Box<dyn $crate::Mask>),
+        }
     };
 
     ($mask : expr, default = $default : expr) => {
-        match mask_from_env($mask, &*DEFAULT_CONVERTERS) {
+        match $crate::env::mask_from_env($mask, None) {
             Some(mask) => mask,
-            None => Box::new($default),
+            None => Ok(Box::new($default) as Box<dyn $crate::Mask>),
         }
     };
 
-    (default = $default : expr) => {
-        match mask_from_env("GPASS_MASK", &*DEFAULT_CONVERTERS) {
-            Some(mask) => mask,
-            None => Box::new($default),
-        }
+    ($mask : expr) => {
+        $crate::env::mask_from_env($mask, None)
     };
 }
 
-#[cfg(feature = "colored")]
-use colored::Color;
-
 #[cfg(feature = "colored")]
 #[macro_export]
 #[doc(hidden)]
 macro_rules! env_color {
-    ($var : expr) => {
-        let res: Result<Color, _> = match std::env::var($var) {
-            Some(val) => val.parse(),
-            None => Err(()),
-        };
-        res
-    };
-
     ($var : expr, $default : expr) => {
-        match env_color!($var) {
-            Ok(color) => color,
-            Err(_) => $default,
-        }
+        $crate::colors::color_from_env($var, $default)
     };
 }
 
@@ -250,18 +129,19 @@ macro_rules! env_color {
 ///The user can also specify the value of the environment variable to read <br>
 /// Alternatively, can also provide a default value (default = ..)<br>
 ///In case the environment variable does not exist, it fallsback to the default mask color defined in colors.rs <br>
+///Returns a `Result<String, String>`, an invalid color is an error. Pass the color to GPass::set_mask_color<br>
 macro_rules! mask_env_color {
     () => {
-        env_color!("GPASS_MASK_COLOR", crate::colors::DEFAULT_MASK_COLOR)
+        $crate::env_color!("GPASS_MASK_COLOR", $crate::colors::DEFAULT_MASK_COLOR)
     };
     (default = $default : expr) => {
-        env_color!("GPASS_MASK_COLOR", $default)
+        $crate::env_color!("GPASS_MASK_COLOR", $default)
     };
     ($var : expr) => {
-        env_color!($var, crate::colors::DEFAULT_MASK_COLOR)
+        $crate::env_color!($var, $crate::colors::DEFAULT_MASK_COLOR)
     };
     ($var : expr , default = $default : expr) => {
-        env_color!($var, $default)
+        $crate::env_color!($var, $default)
     };
 }
 
@@ -269,19 +149,20 @@ macro_rules! mask_env_color {
 #[macro_export]
 ///Macro to get prompt color from the environment using the `GPASS_PROMPT_COLOR` environment variable<br>
 /// Alternatively, can also provide a default value (default = ..)<br>
+///Returns a `Result<String, String>`, an invalid color is an error. Pass the color to GPass::set_prompt_color<br>
 macro_rules! prompt_env_color {
     () => {
-        env_color!("GPASS_PROMPT_COLOR", crate::colors::DEFAULT_PROMPT_COLOR)
+        $crate::env_color!("GPASS_PROMPT_COLOR", $crate::colors::DEFAULT_PROMPT_COLOR)
     };
 
     (default = $default : expr) => {
-        env_color!("GPASS_PROMPT_COLOR", $default)
+        $crate::env_color!("GPASS_PROMPT_COLOR", $default)
     };
 
     ($var : expr) => {
-        env_color!($var, crate::colors::DEFAULT_PROMPT_COLOR)
+        $crate::env_color!($var, $crate::colors::DEFAULT_PROMPT_COLOR)
     };
     ($var : expr , default = $default : expr) => {
-        env_color!($var, $default)
+        $crate::env_color!($var, $default)
     };
 }
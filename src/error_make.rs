@@ -1,4 +1,5 @@
 use crate::LibError;
+use std::path::Path;
 
 impl Default for LibError {
     fn default() -> Self {
@@ -6,6 +7,26 @@ impl Default for LibError {
     }
 }
 
-unsafe impl Send for LibError {}
-unsafe impl Sync for LibError {}
+impl LibError {
+    ///A LibError::Config about a setting, with no file or cause behind it<br>
+    pub fn config(message: impl Into<String>) -> Self {
+        Self::Config {
+            path: None,
+            message: message.into(),
+            source: None,
+        }
+    }
 
+    ///A LibError::Config caused by `source`, about the file at `path` if there is one<br>
+    pub fn config_caused(
+        path: Option<&Path>,
+        message: impl Into<String>,
+        source: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Self {
+        Self::Config {
+            path: path.map(Path::to_path_buf),
+            message: message.into(),
+            source: Some(source.into()),
+        }
+    }
+}
@@ -1,47 +1,316 @@
-use crate::{InputStream, InputToken, LibError};
-use std::io::{Read, Seek};
+use crate::{InputStream, InputToken, KeyDecoder, LibError};
+use std::io::Read;
+use std::str::FromStr;
+use zeroize::{Zeroize, Zeroizing};
 
-///A default implementation of the InputStream trait for any object that implements read and seek functionalities<br> 
-///This can be used for generic custom objects of the user<br> 
-impl<P: Read + Seek> InputStream for P {
-    fn get_token(&mut self) -> Result<InputToken, LibError> {
-        //!Read each byte until we reach a proper unicode character. Or we reach end of file
-        //!Check upto where does it form a valid unicode character, wherever it does, return that as the token
-        //!If w = 0, return EOF
+///Size of the buffer ReaderInput reads ahead into<br>
+pub const DEFAULT_READER_CAPACITY: usize = 256;
 
-        let mut buf = [0u8; 4];
-        let w = self.read(&mut buf).map_err(|e| LibError::IOError(e))?;
+///Text encodings ReaderInput decodes<br>
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    ///ISO-8859-1, every byte is the character with the same code point<br>
+    Latin1,
+}
 
-        if w == 0 {
-            return Ok(InputToken::EOF);
+///Parses `utf-8`, `utf-16le`, `utf-16be` and `latin-1` (or `iso-8859-1`), case insensitive and with or without the dash<br>
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().replace(['-', '_'], "").as_str() {
+            "utf8" => Ok(Encoding::Utf8),
+            "utf16le" => Ok(Encoding::Utf16Le),
+            "utf16be" => Ok(Encoding::Utf16Be),
+            "latin1" | "iso88591" => Ok(Encoding::Latin1),
+            _ => Err(format!("unknown encoding {:?}", s)),
+        }
+    }
+}
+
+///What ReaderInput does with bytes that are not valid in the encoding<br>
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DecodeMode {
+    ///Fail with LibError::InvalidCharacter<br>
+    #[default]
+    Strict,
+    ///Put in U+FFFD (the replacement character) and carry on<br>
+    Lossy,
+}
+
+///Input from anything that implements Read: stdin, a pipe, a child process's stdout, a socket or a file<br>
+///Bytes are read ahead into a buffer and decoded one character at a time, so the reader is never seeked back. Bytes are wiped from the buffer as they are decoded<br>
+///The input is UTF-8 unless set_encoding says otherwise, and a byte order mark at the start picks the encoding and is skipped (see set_bom). Malformed input is an error, or U+FFFD with DecodeMode::Lossy<br>
+///By default a newline (`\n`, `\r` or `\r\n`) ends the input, so a second prompt (ex: the confirmation) reads the following line. With set_end_on_newline(false) the input runs up to end of file and newlines are part of the password<br>
+///Timeouts are not supported, get_token_timeout waits for as long as the reader blocks<br>
+pub struct ReaderInput<R: Read> {
+    reader: R,
+    buf: Box<[u8]>,
+    start: usize,
+    end: usize,
+    //Bytes read ahead and put back, the last one is read first
+    unread: [u8; 4],
+    unread_len: usize,
+    encoding: Encoding,
+    mode: DecodeMode,
+    bom: bool,
+    started: bool,
+    end_on_newline: bool,
+    //The last line ended with `\r`, a `\n` right after it belongs to that line
+    after_cr: bool,
+}
+
+impl<R: Read> ReaderInput<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_capacity(reader, DEFAULT_READER_CAPACITY)
+    }
+
+    ///Reads ahead `capacity` bytes at a time (at least 1)<br>
+    pub fn with_capacity(reader: R, capacity: usize) -> Self {
+        Self {
+            reader,
+            buf: vec![0u8; capacity.max(1)].into_boxed_slice(),
+            start: 0,
+            end: 0,
+            unread: [0; 4],
+            unread_len: 0,
+            encoding: Encoding::default(),
+            mode: DecodeMode::default(),
+            bom: true,
+            started: false,
+            end_on_newline: true,
+            after_cr: false,
+        }
+    }
+
+    ///Encoding of the input, a byte order mark at the start takes precedence unless turned off with set_bom<br>
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+
+    ///Strict (the default) or lossy decoding of malformed input<br>
+    pub fn set_decode_mode(&mut self, mode: DecodeMode) {
+        self.mode = mode;
+    }
+
+    ///Whether a UTF-8 or UTF-16 byte order mark at the start of the input is looked for (the default)<br>
+    ///Without it, a byte order mark is read as the character U+FEFF<br>
+    pub fn set_bom(&mut self, bom: bool) {
+        self.bom = bom;
+    }
+
+    ///Whether a newline ends the input (the default) or is read as part of the password<br>
+    pub fn set_end_on_newline(&mut self, end_on_newline: bool) {
+        self.end_on_newline = end_on_newline;
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    ///The next byte, refilling the buffer when it runs out, None at end of input<br>
+    fn next_byte(&mut self) -> Result<Option<u8>, LibError> {
+        if self.unread_len > 0 {
+            self.unread_len -= 1;
+            return Ok(Some(std::mem::take(&mut self.unread[self.unread_len])));
+        }
+
+        if self.start == self.end {
+            let read = loop {
+                match self.reader.read(&mut self.buf) {
+                    Ok(read) => break read,
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(LibError::IOError(e)),
+                }
+            };
+
+            if read == 0 {
+                return Ok(None);
+            }
+            (self.start, self.end) = (0, read);
         }
 
-        for i in 0..w {
-            if buf[i] & 0b1000_0000 == 0b0000_0000 {
-                //Create a utf-8 object using buf[..i]
-                //Then seek back w-i bytes
-                //Return the utf-8 object as the token char
-
-                let s = std::str::from_utf8(&buf[..(i + 1)])
-                    .map_err(|e| LibError::InvalidCharacter(e.to_string()))?;
-                self.seek(std::io::SeekFrom::Current((w - i - 1) as i64 * -1))
-                    .map_err(|e| LibError::IOError(e))?;
-                match s.chars().next() {
-                    Some(token) => {
-                        return Ok(InputToken::Character(token));
-                    }
-                    None => {
-                        return Err(LibError::InvalidCharacter(
-                            "Invalid UTF-8 sequence".to_string(),
-                        ))
-                    }
+        let byte = std::mem::take(&mut self.buf[self.start]);
+        self.start += 1;
+        Ok(Some(byte))
+    }
+
+    ///Puts bytes back to be read again, in the order given<br>
+    fn unread(&mut self, bytes: &[u8]) {
+        for &byte in bytes.iter().rev() {
+            self.unread[self.unread_len] = byte;
+            self.unread_len += 1;
+        }
+    }
+
+    ///U+FFFD for lossy decoding, an error otherwise<br>
+    fn malformed(&self, message: String) -> Result<Option<char>, LibError> {
+        match self.mode {
+            DecodeMode::Strict => Err(LibError::InvalidCharacter(message)),
+            DecodeMode::Lossy => Ok(Some(char::REPLACEMENT_CHARACTER)),
+        }
+    }
+
+    ///Skips a byte order mark at the start of the input, switching to its encoding<br>
+    fn skip_bom(&mut self) -> Result<(), LibError> {
+        let marks: [(&[u8], Encoding); 3] = [
+            (&[0xef, 0xbb, 0xbf], Encoding::Utf8),
+            (&[0xff, 0xfe], Encoding::Utf16Le),
+            (&[0xfe, 0xff], Encoding::Utf16Be),
+        ];
+
+        let mut seen = Zeroizing::new(Vec::with_capacity(3));
+        while let Some(byte) = self.next_byte()? {
+            seen.push(byte);
+
+            let candidates = marks.iter().filter(|(mark, _)| mark.starts_with(&seen));
+            match candidates.clone().find(|(mark, _)| mark.len() == seen.len()) {
+                Some((_, encoding)) => {
+                    self.encoding = *encoding;
+                    return Ok(());
                 }
+                None if candidates.count() == 0 => break,
+                None => {}
+            }
+        }
+
+        self.unread(&seen);
+        Ok(())
+    }
+
+    ///The next character, None at end of input<br>
+    fn next_char(&mut self) -> Result<Option<char>, LibError> {
+        if !self.started {
+            self.started = true;
+            if self.bom {
+                self.skip_bom()?;
             }
         }
 
-        return Err(LibError::InvalidCharacter(
-            "Invalid UTF-8 sequence".to_string(),
-        ));
+        match self.encoding {
+            Encoding::Utf8 => self.next_utf8(),
+            Encoding::Utf16Le | Encoding::Utf16Be => self.next_utf16(),
+            Encoding::Latin1 => Ok(self.next_byte()?.map(char::from)),
+        }
+    }
+
+    fn next_utf8(&mut self) -> Result<Option<char>, LibError> {
+        let first = match self.next_byte()? {
+            Some(byte) => byte,
+            None => return Ok(None),
+        };
+
+        let len = match first {
+            0x00..=0x7f => 1,
+            0xc2..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf4 => 4,
+            _ => return self.malformed(format!("Invalid UTF-8 start byte {:#04x}", first)),
+        };
+
+        let mut bytes = Zeroizing::new([first, 0, 0, 0]);
+        for i in 1..len {
+            bytes[i] = match self.next_byte()? {
+                Some(byte) if byte & 0b1100_0000 == 0b1000_0000 => byte,
+                Some(byte) => {
+                    //The byte may start the next character
+                    self.unread(&[byte]);
+                    return self.malformed(format!("Invalid UTF-8 continuation byte {:#04x}", byte));
+                }
+                None => {
+                    return self.malformed("Input ends in the middle of a UTF-8 sequence".to_string())
+                }
+            };
+        }
+
+        //Overlong forms and surrogates get through the checks above
+        match std::str::from_utf8(&bytes[..len]).ok().and_then(|s| s.chars().next()) {
+            Some(ch) => Ok(Some(ch)),
+            None => self.malformed("Invalid UTF-8 sequence".to_string()),
+        }
+    }
+
+    ///A UTF-16 code unit from two bytes, in the byte order of the encoding<br>
+    fn unit(&self, bytes: [u8; 2]) -> u16 {
+        match self.encoding {
+            Encoding::Utf16Be => u16::from_be_bytes(bytes),
+            _ => u16::from_le_bytes(bytes),
+        }
+    }
+
+    fn next_utf16(&mut self) -> Result<Option<char>, LibError> {
+        let truncated = || "Input ends in the middle of a UTF-16 character".to_string();
+
+        let first = match (self.next_byte()?, self.next_byte()?) {
+            (None, _) => return Ok(None),
+            (Some(_), None) => return self.malformed(truncated()),
+            (Some(a), Some(b)) => self.unit([a, b]),
+        };
+
+        match first {
+            0xd800..=0xdbff => {}
+            0xdc00..=0xdfff => return self.malformed(format!("Unpaired UTF-16 surrogate {:#06x}", first)),
+            _ => return Ok(char::from_u32(first as u32)),
+        }
+
+        let (a, b) = match (self.next_byte()?, self.next_byte()?) {
+            (Some(a), Some(b)) => (a, b),
+            _ => return self.malformed(truncated()),
+        };
+        let second = self.unit([a, b]);
+
+        match second {
+            0xdc00..=0xdfff => Ok(char::decode_utf16([first, second]).next().and_then(Result::ok)),
+            _ => {
+                //The unit may start the next character
+                self.unread(&[a, b]);
+                self.malformed(format!("Unpaired UTF-16 surrogate {:#06x}", first))
+            }
+        }
+    }
+}
+
+impl ReaderInput<std::io::Stdin> {
+    ///Reads the password from stdin when it is not a terminal, ex: `echo secret | my-program`<br>
+    pub fn stdin() -> Self {
+        Self::new(std::io::stdin())
+    }
+}
+
+impl<R: Read> InputStream for ReaderInput<R> {
+    fn get_token(&mut self) -> Result<InputToken, LibError> {
+        loop {
+            let ch = match self.next_char()? {
+                Some(ch) => ch,
+                None => return Ok(InputToken::EOF),
+            };
+            let after_cr = std::mem::take(&mut self.after_cr);
+
+            match ch {
+                '\n' if self.end_on_newline && after_cr => {}
+                '\n' if self.end_on_newline => return Ok(InputToken::EOF),
+                '\r' if self.end_on_newline => {
+                    self.after_cr = true;
+                    return Ok(InputToken::EOF);
+                }
+                ch => return Ok(InputToken::Character(ch)),
+            }
+        }
+    }
+}
+
+impl<R: Read> Drop for ReaderInput<R> {
+    fn drop(&mut self) {
+        self.buf.zeroize();
+        self.unread.zeroize();
     }
 }
 
@@ -61,31 +330,193 @@ impl InputStream for IString {
         }
 
         let chr = self.0.pop().unwrap();
-        return Ok(InputToken::Character(chr));
+        Ok(InputToken::Character(chr))
     }
 }
 
+#[cfg(not(unix))]
 use getch::Getch as _Getch;
+use std::time::{Duration, Instant};
 
 ///A wrapper over the getch crate to make it work with the InputStream trait (since the upstream ... does not allow to compile otherwise)<br>
-#[repr(transparent)]
-pub struct Getch(_Getch);
+///The bytes are decoded here, so escape sequences (arrows, Home/End, Delete, F-keys) and bracketed pastes come through as their own tokens<br>
+///On unix, stdin is read unbuffered so a timeout can wait on the terminal itself, and the terminal is set up by a terminal::TerminalGuard instead of the getch crate<br>
+///The terminal is only switched over on the first read (unless GPass already did), so constructing a Getch (ex: through GPass::default) does not touch it<br>
+///On Windows, keys outside ASCII come in the console codepage, which is UTF-8 only under `chcp 65001`. Otherwise they fail the prompt with LibError::InvalidCharacter rather than being left out of the password<br>
+pub struct Getch {
+    decoder: KeyDecoder,
+    started: bool,
+
+    #[cfg(unix)]
+    terminal: Option<crate::terminal::TerminalGuard>,
+
+    #[cfg(not(unix))]
+    terminal: Option<_Getch>,
+
+    ///The last byte was the 0xE0 prefix of an extended key<br>
+    #[cfg(not(unix))]
+    extended: bool,
+}
 
 impl Getch {
     pub fn new() -> Self {
-        Self(_Getch::new())
+        Self {
+            decoder: KeyDecoder::new(),
+            started: false,
+            terminal: None,
+
+            #[cfg(not(unix))]
+            extended: false,
+        }
+    }
+
+    ///Take over the terminal on the first read, if nobody holds it yet<br>
+    #[cfg(unix)]
+    fn start(&mut self) -> Result<(), LibError> {
+        if !self.started {
+            self.started = true;
+            self.terminal = crate::terminal::TerminalGuard::acquire().map_err(LibError::IOError)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn start(&mut self) -> Result<(), LibError> {
+        if !self.started {
+            self.started = true;
+            self.terminal = Some(_Getch::new());
+        }
+        Ok(())
+    }
+
+    ///Read a single raw byte from the terminal, None on end of input<br>
+    ///A signal arriving while waiting shows up as an Interrupted error<br>
+    #[cfg(unix)]
+    fn read_byte(&mut self) -> std::io::Result<Option<u8>> {
+        let mut byte = 0u8;
+        let buf = &mut byte as *mut u8 as *mut libc::c_void;
+        match unsafe { libc::read(libc::STDIN_FILENO, buf, 1) } {
+            0 => Ok(None),
+            1 => Ok(Some(byte)),
+            _ => Err(std::io::Error::last_os_error()),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn read_byte(&mut self) -> std::io::Result<Option<u8>> {
+        match self.terminal {
+            Some(ref terminal) => terminal.getch().map(Some),
+            None => Ok(None),
+        }
+    }
+
+    ///Wait until there is something to read on the terminal, false if the deadline passed first<br>
+    #[cfg(unix)]
+    fn wait(&mut self, deadline: Instant) -> std::io::Result<bool> {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let mut fd = libc::pollfd {
+            fd: libc::STDIN_FILENO,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+
+        //Round up, so we never spin on a sub-millisecond remainder
+        let millis = remaining.as_micros().div_ceil(1000).min(i32::MAX as u128) as i32;
+        match unsafe { libc::poll(&mut fd, 1, millis) } {
+            0 => Ok(false),
+            n if n > 0 => Ok(true),
+            _ => Err(std::io::Error::last_os_error()),
+        }
+    }
+
+    ///Timeouts are not supported on this platform, input is waited for indefinitely<br>
+    #[cfg(not(unix))]
+    fn wait(&mut self, _deadline: Instant) -> std::io::Result<bool> {
+        Ok(true)
+    }
+
+    ///_getch hands over extended keys (arrows, Home, End, Delete, F11 and F12) as 0xE0 followed by a scan code<br>
+    ///The getch crate already drops the keys prefixed with 0x00 (F1 to F10)<br>
+    #[cfg(not(unix))]
+    fn scan_code(byte: u8) -> InputToken {
+        match byte {
+            0x4b => InputToken::Left,
+            0x4d => InputToken::Right,
+            0x47 => InputToken::Home,
+            0x4f => InputToken::End,
+            0x53 => InputToken::Delete,
+            0x73 => InputToken::WordLeft,
+            0x74 => InputToken::WordRight,
+            0x85 => InputToken::Function(11),
+            0x86 => InputToken::Function(12),
+            _ => InputToken::IgnoreToken,
+        }
+    }
+
+    fn next_token(&mut self, deadline: Option<Instant>) -> Result<InputToken, LibError> {
+        self.start()?;
+
+        loop {
+            #[cfg(unix)]
+            if crate::terminal::take_redraw() {
+                return Ok(InputToken::Redraw);
+            }
+
+            //Interrupted by a signal: check for a resume or resize and wait again
+            let interrupted = |e: &std::io::Error| e.kind() == std::io::ErrorKind::Interrupted;
+
+            if let Some(deadline) = deadline {
+                match self.wait(deadline) {
+                    Ok(true) => {}
+                    Ok(false) => return Err(LibError::Timeout),
+                    Err(e) if interrupted(&e) => continue,
+                    Err(e) => return Err(LibError::IOError(e)),
+                }
+            }
+
+            let byte = match self.read_byte() {
+                Ok(Some(byte)) => byte,
+                Ok(None) => return Ok(InputToken::EOF),
+                Err(e) if interrupted(&e) => continue,
+                Err(e) => return Err(LibError::IOError(e)),
+            };
+
+            #[cfg(not(unix))]
+            {
+                if std::mem::take(&mut self.extended) {
+                    return Ok(Self::scan_code(byte));
+                }
+                if byte == 0xe0 && !self.decoder.is_pending() {
+                    self.extended = true;
+                    continue;
+                }
+            }
+
+            match self.decoder.push(byte) {
+                Ok(Some(token)) => return Ok(token),
+                Ok(None) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Default for Getch {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl InputStream for Getch {
     fn get_token(&mut self) -> Result<InputToken, LibError> {
-        let ch = self.0.getch().map_err(|e| LibError::IOError(e))? as char;
+        self.next_token(None)
+    }
 
-        match ch {
-            '\x08' | '\x7f' => Ok(InputToken::Backspace),
-            '\n' | '\r' => Ok(InputToken::EOF),
-            '\x03' => Err(LibError::UserInterrupt),
-            _ => Ok(InputToken::Character(ch)),
-        }
+    fn get_token_timeout(&mut self, timeout: Duration) -> Result<InputToken, LibError> {
+        self.next_token(Some(Instant::now() + timeout))
+    }
+
+    fn is_terminal(&self) -> bool {
+        true
     }
 }
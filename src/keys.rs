@@ -0,0 +1,277 @@
+//!Decoder for raw keyboard bytes coming from a terminal<br>
+//!Turns control characters and ANSI/VT escape sequences (CSI `ESC [` and SS3 `ESC O`) into input tokens<br>
+//!Sequences that are not understood are dropped instead of ending up in the password<br>
+//!Multi-byte UTF-8 characters are put together across bytes, so non-ASCII keystrokes come through as a single character<br>
+//!Bracketed pastes (`ESC [200~` ... `ESC [201~`) come through as a single Paste token, with nothing inside them treated as a key<br>
+
+use crate::{InputToken, LibError};
+use zeroize::Zeroize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum State {
+    #[default]
+    Ground,
+    Escape,
+    Csi,
+    Ss3,
+    ///Waiting for this many UTF-8 continuation bytes<br>
+    Utf8(usize),
+    ///Inside a bracketed paste<br>
+    Paste,
+}
+
+const PASTE_START: &[u8] = b"200";
+const PASTE_END: &[u8] = b"\x1b[201~";
+///Longest paste taken, in bytes. The buffer is allocated once at this size, so it never moves and leaves copies behind<br>
+const MAX_PASTE: usize = 4096;
+
+///Byte by byte decoder for keystrokes<br>
+///Feed it every byte read from the terminal, it hands back a token once a whole key has been read<br>
+///Pastes longer than 4096 bytes are dropped (a single IgnoreToken), no password is that long<br>
+///Note: a lone Escape press cannot be told apart from the start of a sequence, so it is merged with the key that follows it (a printable key comes through as Alt, control keys as themselves)<br>
+#[derive(Debug, Default)]
+pub struct KeyDecoder {
+    state: State,
+    params: Vec<u8>,
+    utf8: Vec<u8>,
+    paste: Vec<u8>,
+    ///The paste did not fit in the buffer<br>
+    overflow: bool,
+    alt: bool,
+}
+
+impl KeyDecoder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    ///Feed one byte to the decoder<br>
+    ///Returns Ok(None) while in the middle of a sequence<br>
+    pub fn push(&mut self, byte: u8) -> Result<Option<InputToken>, LibError> {
+        match self.state {
+            State::Ground if byte == 0x1b => {
+                self.state = State::Escape;
+                Ok(None)
+            }
+            State::Ground if byte >= 0x80 => self.utf8_start(byte, false),
+            State::Ground => Self::ground(byte).map(Some),
+            State::Escape if byte >= 0x80 => self.utf8_start(byte, true),
+            //A stray Escape press followed by Enter, Backspace, Ctrl-C...: the key goes through as if typed alone
+            State::Escape if byte != 0x1b && (byte < 0x20 || byte == 0x7f) => {
+                self.state = State::Ground;
+                Self::ground(byte).map(Some)
+            }
+            State::Escape => Ok(self.escape(byte)),
+            State::Utf8(remaining) => self.utf8_continue(byte, remaining),
+            State::Csi => Ok(self.csi(byte)),
+            State::Paste => self.paste(byte),
+            State::Ss3 => {
+                self.state = State::Ground;
+                Ok(Some(Self::ss3(byte)))
+            }
+        }
+    }
+
+    ///Whether the decoder is in the middle of a sequence<br>
+    pub fn is_pending(&self) -> bool {
+        self.state != State::Ground
+    }
+
+    fn ground(byte: u8) -> Result<InputToken, LibError> {
+        //Emacs style control keys for moving around the password
+        match byte {
+            0x08 | 0x7f => Ok(InputToken::Backspace),
+            0x04 => Ok(InputToken::Delete),
+            0x02 => Ok(InputToken::Left),
+            0x06 => Ok(InputToken::Right),
+            0x01 => Ok(InputToken::Home),
+            0x05 => Ok(InputToken::End),
+            b'\n' | b'\r' => Ok(InputToken::EOF),
+            0x03 => Err(LibError::UserInterrupt),
+            0x00..=0x1f => Ok(InputToken::IgnoreToken),
+            _ => Ok(InputToken::Character(char::from(byte))),
+        }
+    }
+
+    fn utf8_start(&mut self, byte: u8, alt: bool) -> Result<Option<InputToken>, LibError> {
+        let remaining = match byte {
+            0xc2..=0xdf => 1,
+            0xe0..=0xef => 2,
+            0xf0..=0xf4 => 3,
+            _ => {
+                self.state = State::Ground;
+                return Err(LibError::InvalidCharacter(format!(
+                    "Invalid UTF-8 start byte {:#04x}",
+                    byte
+                )));
+            }
+        };
+
+        self.utf8.clear();
+        self.utf8.push(byte);
+        self.alt = alt;
+        self.state = State::Utf8(remaining);
+        Ok(None)
+    }
+
+    fn utf8_continue(
+        &mut self,
+        byte: u8,
+        remaining: usize,
+    ) -> Result<Option<InputToken>, LibError> {
+        if byte & 0b1100_0000 != 0b1000_0000 {
+            self.state = State::Ground;
+            return Err(LibError::InvalidCharacter(format!(
+                "Invalid UTF-8 continuation byte {:#04x}",
+                byte
+            )));
+        }
+
+        self.utf8.push(byte);
+        if remaining > 1 {
+            self.state = State::Utf8(remaining - 1);
+            return Ok(None);
+        }
+
+        self.state = State::Ground;
+        let ch = std::str::from_utf8(&self.utf8)
+            .map_err(|e| LibError::InvalidCharacter(e.to_string()))
+            .map(|s| s.chars().next());
+        self.utf8.zeroize();
+
+        let ch = ch?
+            .ok_or_else(|| LibError::InvalidCharacter("Invalid UTF-8 sequence".to_string()))?;
+
+        Ok(Some(match self.alt {
+            true => InputToken::Alt(ch),
+            false => InputToken::Character(ch),
+        }))
+    }
+
+    fn escape(&mut self, byte: u8) -> Option<InputToken> {
+        self.state = State::Ground;
+
+        match byte {
+            b'[' => {
+                self.state = State::Csi;
+                self.params.clear();
+                None
+            }
+            b'O' => {
+                self.state = State::Ss3;
+                None
+            }
+            //Some terminals send Alt+key as a doubled escape, keep waiting for the actual sequence
+            0x1b => {
+                self.state = State::Escape;
+                None
+            }
+            0x20..=0x7e => Some(InputToken::Alt(byte as char)),
+            _ => Some(InputToken::IgnoreToken),
+        }
+    }
+
+    fn csi(&mut self, byte: u8) -> Option<InputToken> {
+        match byte {
+            //Parameter and intermediate bytes
+            0x20..=0x3f => {
+                self.params.push(byte);
+                None
+            }
+            b'~' if self.params == PASTE_START => {
+                self.state = State::Paste;
+                self.paste = Vec::with_capacity(MAX_PASTE + PASTE_END.len());
+                self.overflow = false;
+                None
+            }
+            //Final byte
+            0x40..=0x7e => {
+                self.state = State::Ground;
+                Some(self.csi_final(byte))
+            }
+            //Anything else is a broken sequence
+            _ => {
+                self.state = State::Ground;
+                Some(InputToken::IgnoreToken)
+            }
+        }
+    }
+
+    fn csi_final(&self, byte: u8) -> InputToken {
+        let params = String::from_utf8_lossy(&self.params);
+        let mut params = params.split(';').map(|p| p.parse::<u16>().ok());
+        let first = params.next().flatten();
+
+        //xterm modifier parameter: 3 = Alt, 5 = Ctrl
+        let modified = matches!(params.next().flatten(), Some(3) | Some(5));
+
+        match (byte, first) {
+            (b'C', _) if modified => InputToken::WordRight,
+            (b'D', _) if modified => InputToken::WordLeft,
+            (b'C', _) => InputToken::Right,
+            (b'D', _) => InputToken::Left,
+            (b'H', _) => InputToken::Home,
+            (b'F', _) => InputToken::End,
+            (b'~', Some(1) | Some(7)) => InputToken::Home,
+            (b'~', Some(4) | Some(8)) => InputToken::End,
+            (b'~', Some(3)) => InputToken::Delete,
+            (b'~', Some(n @ 11..=15)) => InputToken::Function((n - 10) as u8),
+            (b'~', Some(n @ 17..=21)) => InputToken::Function((n - 11) as u8),
+            (b'~', Some(n @ 23..=24)) => InputToken::Function((n - 12) as u8),
+            _ => InputToken::IgnoreToken,
+        }
+    }
+
+    fn paste(&mut self, byte: u8) -> Result<Option<InputToken>, LibError> {
+        //Once full, only the bytes that may be the start of the end marker are kept, in place
+        if self.paste.len() == self.paste.capacity() {
+            let keep = self.paste.len() - (PASTE_END.len() - 1);
+            self.paste.copy_within(keep.., 0);
+            self.paste[PASTE_END.len() - 1..].zeroize();
+            self.paste.truncate(PASTE_END.len() - 1);
+            self.overflow = true;
+        }
+
+        self.paste.push(byte);
+        if !self.paste.ends_with(PASTE_END) {
+            return Ok(None);
+        }
+
+        self.state = State::Ground;
+        if self.overflow {
+            self.paste.zeroize();
+            return Ok(Some(InputToken::IgnoreToken));
+        }
+        self.paste.truncate(self.paste.len() - PASTE_END.len());
+
+        //The buffer is handed over to the string as is, so no stray copy of the paste is left behind
+        match String::from_utf8(std::mem::take(&mut self.paste)) {
+            Ok(text) => Ok(Some(InputToken::Paste(text))),
+            Err(e) => {
+                e.into_bytes().zeroize();
+                Err(LibError::InvalidCharacter(
+                    "Pasted text is not valid UTF-8".to_string(),
+                ))
+            }
+        }
+    }
+
+    fn ss3(byte: u8) -> InputToken {
+        match byte {
+            b'C' => InputToken::Right,
+            b'D' => InputToken::Left,
+            b'H' => InputToken::Home,
+            b'F' => InputToken::End,
+            b'P'..=b'S' => InputToken::Function(byte - b'P' + 1),
+            _ => InputToken::IgnoreToken,
+        }
+    }
+}
+
+///A paste or character cut short by the end of the input is not left behind<br>
+impl Drop for KeyDecoder {
+    fn drop(&mut self) {
+        self.paste.zeroize();
+        self.utf8.zeroize();
+    }
+}
@@ -4,17 +4,67 @@
 #![allow(unused_macros)]
 #![doc = include_str!("../README.md")]
 
+use std::io::IsTerminal;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 pub mod masks;
+pub mod render;
+pub mod script;
+pub mod spec;
+pub mod testing;
+
+mod registry;
+pub use registry::{MaskFactory, MaskInfo, MaskRegistry, Schema};
 
 #[cfg(feature = "reverse")]
 pub mod reverse;
 
+#[cfg(feature = "strength")]
+pub mod strength;
+
 // mod output_stream;
 // pub use output_stream::Output;
 
 mod file_input;
-pub use file_input::{Getch, IString};
+pub use file_input::{DecodeMode, Encoding, Getch, IString, ReaderInput, DEFAULT_READER_CAPACITY};
+
+mod secret;
+pub use secret::{Secret, DEFAULT_SECRET_CAPACITY};
+
+mod buffer;
+pub use buffer::{Edit, EditBuffer, Motion};
+
+pub mod policy;
+use policy::{Policy, Validator};
+use render::{display_width, Glyph};
+
+mod timeout;
+pub use timeout::Timeout;
+
+mod session;
+
+mod confirm;
+pub use confirm::{
+    Confirm, DEFAULT_CONFIRM_ATTEMPTS, DEFAULT_CONFIRM_PROMPT, DEFAULT_MISMATCH_MESSAGE,
+};
+
+mod keys;
+pub use keys::KeyDecoder;
+
+mod paste;
+pub use paste::{PastePolicy, DEFAULT_PASTE_REJECT_MESSAGE};
+
+#[cfg(unix)]
+pub mod terminal;
+
+#[cfg(all(target_os = "linux", feature = "pty"))]
+pub mod pty;
+
+#[cfg(feature = "tokio")]
+pub mod async_input;
+
+#[cfg(feature = "config")]
+pub mod config;
 
 //Allow users to use macros from env.rs
 #[cfg(feature = "env")]
@@ -25,15 +75,37 @@ pub mod env;
 pub mod colors;
 
 #[cfg(feature = "colored")]
-use colored::Colorize;
+use colors::{ColorChoice, Theme};
 
 ///Token for implementation of the input stream<br> <br>
-#[derive(Debug, Clone, Default, Copy)]
+#[derive(Debug, Clone, Default)]
 pub enum InputToken {
     ///A known unicode character<br>
     Character(char),
-    ///Backspace/Delete character input from keyboards<br>
+    ///Backspace character input from keyboards, removes the character before the cursor<br>
     Backspace,
+    ///Delete-forward, removes the character under the cursor<br>
+    Delete,
+    ///Cursor one character to the left<br>
+    Left,
+    ///Cursor one character to the right<br>
+    Right,
+    ///Cursor to the start of the password<br>
+    Home,
+    ///Cursor to the end of the password<br>
+    End,
+    ///Cursor to the start of the previous word<br>
+    WordLeft,
+    ///Cursor to the end of the next word<br>
+    WordRight,
+    ///Function key F1-F12<br>
+    Function(u8),
+    ///Key pressed with Alt held down (sent by terminals as Escape followed by the key)<br>
+    Alt(char),
+    ///Text pasted into the terminal in one go (bracketed paste)<br>
+    Paste(String),
+    ///The terminal was handed back to the program (ex: `fg` after Ctrl-Z) or resized, the prompt and password are to be drawn again<br>
+    Redraw,
     ///End of Input<br>
     EOF,
     ///These are for special cases where the token is to be ignored, and not appended to the password<br>
@@ -41,66 +113,206 @@ pub enum InputToken {
     IgnoreToken,
 }
 
-#[derive(Debug)]
+impl InputToken {
+    ///The cursor movement for navigation tokens, None for every other token<br>
+    pub fn motion(&self) -> Option<Motion> {
+        match self {
+            InputToken::Left => Some(Motion::Left),
+            InputToken::Right => Some(Motion::Right),
+            InputToken::Home => Some(Motion::Home),
+            InputToken::End => Some(Motion::End),
+            InputToken::WordLeft | InputToken::Alt('b') => Some(Motion::WordLeft),
+            InputToken::WordRight | InputToken::Alt('f') => Some(Motion::WordRight),
+            _ => None,
+        }
+    }
+}
+
 ///Library Error Class<br>
+///Errors carry their cause as a source (see std::error::Error::source), and are Send + Sync so they can cross threads and tasks<br>
+#[derive(Debug, thiserror::Error)]
 pub enum LibError {
+    #[error("invalid character in the input: {0}")]
     InvalidCharacter(String),
 
     ///For errors occured during pop_password, feed_password and end_password<br>
-    PasswordCRUDFailure(String),
+    #[error("the mask could not update the password")]
+    PasswordCRUDFailure(#[from] MaskError),
+
+    #[error("interrupted by the user")]
     UserInterrupt,
-    IOError(std::io::Error),
+
+    ///No input arrived before the timeout set on GPass<br>
+    #[error("timed out waiting for the password")]
+    Timeout,
+
+    ///The password and its confirmation did not match, after the given number of attempts<br>
+    #[error("the password and its confirmation did not match after {0} attempts")]
+    ConfirmationFailed(usize),
+
+    ///The password did not pass the policy within the allowed attempts, holds the last explanation<br>
+    #[error("{0}")]
+    PolicyViolation(String),
+
+    ///A mask spec that could not be parsed or built, see the spec module<br>
+    #[error("invalid mask")]
+    InvalidMask(#[from] spec::SpecError),
+
+    ///A config file or setting that could not be used, see the config module<br>
+    ///`path` is the file at fault if there is one, `source` the error underneath (ex: the io::Error or toml::de::Error), see LibError::config<br>
+    #[error("invalid configuration: {message}")]
+    Config {
+        path: Option<std::path::PathBuf>,
+        message: String,
+        #[source]
+        source: Option<Box<dyn std::error::Error + Send + Sync>>,
+    },
+
+    #[error(transparent)]
+    IOError(#[from] std::io::Error),
+
+    #[error("{0}")]
     Other(String),
+
+    #[error("undefined behavior: {0}")]
     UndefinedBehavior(String),
-    ForeignLibrary(Box<dyn std::error::Error>),
+
+    #[error(transparent)]
+    ForeignLibrary(Box<dyn std::error::Error + Send + Sync>),
 }
 
 pub mod error_make;
 
-impl std::fmt::Display for LibError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?} ", self)
+///Error type for the Mask trait<br>
+#[derive(Debug, thiserror::Error)]
+pub enum MaskError {
+    ///Writing to the output stream failed<br>
+    #[error("could not write the mask output")]
+    IOError(#[from] std::io::Error),
+
+    ///Anything else, with a message<br>
+    #[error("{0}")]
+    Other(String),
+}
+
+impl From<String> for MaskError {
+    fn from(message: String) -> Self {
+        Self::Other(message)
     }
 }
 
-impl std::error::Error for LibError {}
+impl From<&str> for MaskError {
+    fn from(message: &str) -> Self {
+        Self::Other(message.to_string())
+    }
+}
 
 ///Trait for character input streams (ex : Getch)<br><br>
 ///This is used to get character by character input from user and analyse it<br> <br>
 pub trait InputStream {
     ///Getting input tokens from the input source, example keystrokes from a keyboard or characters from a file or bytes from a network source<br>
     fn get_token(&mut self) -> Result<InputToken, LibError>;
+
+    ///Same as get_token, but gives up with LibError::Timeout if no token arrives within `timeout`<br>
+    ///The default implementation cannot interrupt get_token, so it waits indefinitely<br>
+    fn get_token_timeout(&mut self, _timeout: Duration) -> Result<InputToken, LibError> {
+        self.get_token()
+    }
+
+    ///Whether the stream reads keystrokes from the terminal on stdin<br>
+    ///If so, GPass::get_password holds a terminal::TerminalGuard (on unix) for as long as the prompt runs<br>
+    fn is_terminal(&self) -> bool {
+        false
+    }
 }
 
 ///Trait for password masks<br><br>
-///Implemented by all the masks in this library(Standard, Blind, Echo, OneReverse, MimiReverse)<br><br>
+///This is the incremental interface GPass drives: every edit is applied to the password and drawn by the mask itself<br>
+///The masks in this library (Standard, Blind, Echo, OneReverse, MimiReverse) implement render::RenderMask instead, and get this trait through the adapter in the render module. New masks should do the same<br><br>
 pub trait Mask {
     ///A default implementation for the mask<br>
     fn default() -> Self
     where
         Self: Sized;
 
-    ///To add a character to the password<br>
+    ///To insert a character into the password at the cursor<br>
     fn feed_password(
         &self,
-        password: &mut String,
+        password: &mut EditBuffer,
         ch: char,
         o: &mut dyn std::io::Write,
-    ) -> Result<(), String>;
+    ) -> Result<(), MaskError>;
 
-    ///To pop the last character from the password<br>
-    fn pop_password(&self, password: &mut String, o: &mut dyn std::io::Write)
-        -> Result<(), String>;
+    ///To insert pasted text into the password at the cursor<br>
+    ///The default implementation feeds the characters one by one, masks should override it to redraw once for the whole paste<br>
+    fn paste_password(
+        &self,
+        password: &mut EditBuffer,
+        text: &str,
+        o: &mut dyn std::io::Write,
+    ) -> Result<(), MaskError> {
+        text.chars()
+            .try_for_each(|ch| self.feed_password(password, ch, o))
+    }
+
+    ///To draw the whole password again, right after the prompt has been printed anew<br>
+    ///The default implementation replays the password through feed_password and move_cursor<br>
+    fn redraw_password(
+        &self,
+        password: &EditBuffer,
+        o: &mut dyn std::io::Write,
+    ) -> Result<(), MaskError> {
+        let mut replay = EditBuffer::new();
+        for ch in password.expose().chars() {
+            self.feed_password(&mut replay, ch, o)?;
+        }
+        for _ in password.cursor()..password.len() {
+            self.move_cursor(&mut replay, Motion::Left, o)?;
+        }
+        Ok(())
+    }
+
+    ///To remove the character before the cursor (backspace)<br>
+    fn pop_password(
+        &self,
+        password: &mut EditBuffer,
+        o: &mut dyn std::io::Write,
+    ) -> Result<(), MaskError>;
+
+    ///To remove the character under the cursor (delete-forward)<br>
+    fn delete_password(
+        &self,
+        password: &mut EditBuffer,
+        o: &mut dyn std::io::Write,
+    ) -> Result<(), MaskError>;
+
+    ///To move the cursor within the password<br>
+    fn move_cursor(
+        &self,
+        password: &mut EditBuffer,
+        motion: Motion,
+        o: &mut dyn std::io::Write,
+    ) -> Result<(), MaskError>;
 
     ///For any ending procedure(once the user has provided EOF)<br>
-    fn end_password(&self, o: &mut dyn std::io::Write) -> Result<(), String> {
-        o.write(b"\n").map_err(|e| e.to_string())?;
+    fn end_password(
+        &self,
+        _password: &EditBuffer,
+        o: &mut dyn std::io::Write,
+    ) -> Result<(), MaskError> {
+        o.write_all(b"\n")?;
         Ok(())
     }
 
     #[cfg(feature = "colored")]
     ///Set a color for the mask<br>
-    fn set_color(&mut self, _c: &str) -> ();
+    fn set_color(&mut self, _c: &str);
+
+    #[cfg(feature = "colored")]
+    ///Take the styles of a theme that concern the mask, the default implementation only takes the mask color<br>
+    fn set_theme(&mut self, theme: &colors::Theme) {
+        self.set_color(&theme.mask);
+    }
 }
 
 ///Final main struct of the library <br>
@@ -110,7 +322,13 @@ pub trait Mask {
 ///A mask(Standard/Blind/Echo/OneReverse/MimiReverse or user custom)<br>
 ///A prompt for the password input <br>
 ///A boolean to decide whether to return an error on user interrupt or just return the password collected till then<br>
-///Prompt color(only if  `colored` feature is enabled)<br>
+///An optional confirmation prompt, to ask for the password twice<br>
+///An optional policy the password has to pass<br>
+///An optional timeout for the prompt<br>
+///A policy for pasted text<br>
+///An optional terminal width, to override the one queried from the terminal<br>
+///An optional placeholder shown until something is typed<br>
+///Theme and when to use colors(only if  `colored` feature is enabled)<br>
 ///For custom user implementations, use ex:
 /// ```rust<br>
 ///     let gpass = GPass{
@@ -134,9 +352,37 @@ pub struct GPass {
     ///Prompt for the password<br>
     pub prompt: String,
 
+    ///Ask for the password a second time and require both entries to match<br>
+    pub confirm: Option<Confirm>,
+
+    ///Rules the password has to pass, the prompt repeats with an explanation until it does<br>
+    pub policy: Option<Policy>,
+
+    ///Give up with LibError::Timeout after an idle or total duration<br>
+    pub timeout: Option<Timeout>,
+
+    ///What to do with text pasted into the prompt<br>
+    pub paste: PastePolicy,
+
+    ///Width of the terminal in columns, queried from the terminal (on unix) when None<br>
+    ///Passwords that do not fit on the row after the prompt are scrolled horizontally<br>
+    pub columns: Option<usize>,
+
+    ///Whether output_stream is a terminal, true for the default stream (stderr) when it is one<br>
+    ///Streams passed to GPass::new are assumed not to be, set_output_stream works it out<br>
+    pub output_is_terminal: bool,
+
+    ///Hint shown after the prompt while nothing has been typed, with masks based on render::RenderMask<br>
+    pub placeholder: Option<String>,
+
     #[cfg(feature = "colored")]
-    ///Color for the prompt<br>
-    pub prompt_color: Arc<str>,
+    ///Styles for the prompt, the mask and the messages, see colors::Theme<br>
+    ///Change it with set_theme so the mask gets its part<br>
+    pub theme: Theme,
+
+    #[cfg(feature = "colored")]
+    ///When to color the prompt and mask, by default only on terminals and unless NO_COLOR is set<br>
+    pub color_choice: ColorChoice,
 }
 
 impl Default for GPass {
@@ -147,9 +393,18 @@ impl Default for GPass {
             mask: Box::new(masks::Standard::default()),
             output_stream: Box::new(std::io::stderr()),
             ctrl_c_abort: true,
+            confirm: None,
+            policy: None,
+            timeout: None,
+            paste: PastePolicy::default(),
+            columns: None,
+            output_is_terminal: std::io::stderr().is_terminal(),
+            placeholder: None,
 
             #[cfg(feature = "colored")]
-            prompt_color: Arc::from(colors::DEFAULT_PROMPT_COLOR),
+            theme: Theme::default(),
+            #[cfg(feature = "colored")]
+            color_choice: ColorChoice::default(),
         }
     }
 }
@@ -168,34 +423,99 @@ impl GPass {
             mask,
             output_stream,
             ctrl_c_abort,
+            confirm: None,
+            policy: None,
+            timeout: None,
+            paste: PastePolicy::default(),
+            columns: None,
+            output_is_terminal: false,
+            placeholder: None,
 
             #[cfg(feature = "colored")]
-            prompt_color: Arc::from(colors::DEFAULT_PROMPT_COLOR),
+            theme: Theme::default(),
+            #[cfg(feature = "colored")]
+            color_choice: ColorChoice::default(),
         }
     }
 
+    ///Write to `output`, noting whether it is a terminal<br>
+    pub fn set_output_stream<W: std::io::Write + IsTerminal + 'static>(&mut self, output: W) {
+        self.output_is_terminal = output.is_terminal();
+        self.output_stream = Box::new(output);
+    }
+
+    ///Color for the prompt, see colors for the values taken<br>
     #[cfg(feature = "colored")]
     pub fn set_prompt_color(&mut self, c: &str) {
-        self.prompt_color = Arc::from(c);
+        self.theme.prompt = c.to_string();
     }
 
+    ///Color for the mask, see colors for the values taken<br>
     #[cfg(feature = "colored")]
     pub fn set_mask_color(&mut self, c: &str) {
-        let _ = self.mask.set_color(c);
+        self.theme.mask = c.to_string();
+        self.mask.set_color(c);
+    }
+
+    ///Style every part of the prompt, ex: `gpass.set_theme(colors::Theme::named("mono").unwrap())`<br>
+    ///The mask takes its styles from the theme too, so set the mask first<br>
+    #[cfg(feature = "colored")]
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.mask.set_theme(&theme);
+        self.theme = theme;
+    }
+
+    ///Show `placeholder` after the prompt until something is typed, ex: "(hidden)"<br>
+    pub fn set_placeholder(&mut self, placeholder: &str) {
+        self.placeholder = Some(placeholder.to_string());
+    }
+
+    ///Always, never or automatically color the prompt and mask, see colors::ColorChoice<br>
+    #[cfg(feature = "colored")]
+    pub fn set_color_choice(&mut self, choice: ColorChoice) {
+        self.color_choice = choice;
+    }
+
+    ///The theme named by `GPASS_THEME`, then prompt and mask colors from `GPASS_PROMPT_COLOR` and `GPASS_MASK_COLOR`<br>
+    ///Colors that are not set come from the theme, or are the defaults without one<br>
+    #[cfg(feature = "colored")]
+    pub fn set_colors_from_env(&mut self) -> Result<(), LibError> {
+        if let Some(theme) = Theme::from_env().map_err(LibError::config)? {
+            self.set_theme(theme);
+            let (prompt, mask) = (self.theme.prompt.clone(), self.theme.mask.clone());
+            self.set_prompt_color(&colors::color_from_env("GPASS_PROMPT_COLOR", &prompt).map_err(LibError::config)?);
+            self.set_mask_color(&colors::color_from_env("GPASS_MASK_COLOR", &mask).map_err(LibError::config)?);
+            return Ok(());
+        }
+
+        self.set_prompt_color(&colors::prompt_color_from_env().map_err(LibError::config)?);
+        self.set_mask_color(&colors::mask_color_from_env().map_err(LibError::config)?);
+        Ok(())
     }
 
-    fn prompt_print(&mut self) -> Result<(), std::io::Error> {
-        let aftermath = match self.prompt.chars().last() {
+    ///Drops the colors if they are not to be used, see ColorChoice<br>
+    fn settle_colors(&mut self) {
+        #[cfg(feature = "colored")]
+        if !self.color_choice.enabled(self.output_is_terminal) {
+            self.set_theme(Theme::plain());
+        }
+    }
+
+    ///Separator printed after the prompt<br>
+    fn aftermath(prompt: &str) -> &'static str {
+        match prompt.chars().last() {
             Some(ch) if ch.is_ascii_whitespace() => "",
             _ => ": ",
-        };
+        }
+    }
 
-        let prompt = &self.prompt;
+    fn prompt_print(&mut self, prompt: &str) -> Result<(), std::io::Error> {
+        let aftermath = Self::aftermath(prompt);
 
         #[cfg(feature = "colored")]
-        let prompt = prompt.to_string().color(self.prompt_color.as_ref());
+        let prompt = colors::paint(prompt, &self.theme.prompt);
         #[cfg(feature = "colored")]
-        let aftermath = aftermath.color(self.prompt_color.as_ref());
+        let aftermath = colors::paint(aftermath, &self.theme.prompt);
 
         // self.output_stream.write(prompt.as_bytes())?;
         // self.output_stream.write(aftermath.as_bytes())?;
@@ -214,54 +534,248 @@ impl GPass {
         self.prompt = prompt.to_string();
     }
 
-    pub fn get_password(mut self) -> Result<String, LibError> {
-        let mut password = String::with_capacity(25); //Default capacity
+    ///Ask for the password twice, see Confirm<br>
+    pub fn set_confirm(&mut self, confirm: Confirm) {
+        self.confirm = Some(confirm);
+    }
+
+    ///Validate the password against a policy, see policy::Policy<br>
+    pub fn set_policy(&mut self, policy: Policy) {
+        self.policy = Some(policy);
+    }
+
+    ///Stop waiting for input after an idle or total duration, see Timeout<br>
+    pub fn set_timeout(&mut self, timeout: Timeout) {
+        self.timeout = Some(timeout);
+    }
+
+    ///Allow, trim or reject pasted text, see PastePolicy<br>
+    pub fn set_paste_policy(&mut self, paste: PastePolicy) {
+        self.paste = paste;
+    }
+
+    ///Assume a terminal `columns` wide instead of querying it<br>
+    pub fn set_columns(&mut self, columns: usize) {
+        self.columns = Some(columns);
+    }
+
+    ///Cells left on the terminal row after the prompt, keeping the last column free for the cursor<br>
+    fn view_width(&self, prompt: &str) -> Option<usize> {
+        #[cfg(unix)]
+        let queried = terminal::columns;
+        #[cfg(not(unix))]
+        let queried = || None;
+
+        let columns = self.columns.or_else(queried)?;
+        let taken = render::display_width(prompt) + render::display_width(Self::aftermath(prompt));
+        Some(columns.saturating_sub(taken + 1).max(1))
+    }
+
+    ///An empty password buffer, sized to the terminal row after `prompt`<br>
+    fn edit_buffer(&self, prompt: &str) -> EditBuffer {
+        let mut password = EditBuffer::new();
+        password.set_view_width(self.view_width(prompt));
 
-        self.prompt_print().map_err(|e| LibError::IOError(e))?;
+        if let Some(ref hint) = self.placeholder {
+            #[cfg(feature = "colored")]
+            let text = colors::paint(hint, &self.theme.placeholder);
+            #[cfg(not(feature = "colored"))]
+            let text = hint;
+
+            password.set_placeholder(Some(Glyph::new(text, display_width(hint))));
+        }
+        password
+    }
+
+    ///Prints the prompt, followed by the placeholder if the buffer has one<br>
+    fn start_line(&mut self, prompt: &str, password: &EditBuffer) -> Result<(), LibError> {
+        self.prompt_print(prompt).map_err(LibError::IOError)?;
 
-        let mut ch;
+        if password.placeholder().is_some() {
+            self.mask
+                .redraw_password(password, self.output_stream.as_mut())
+                .map_err(LibError::PasswordCRUDFailure)?;
+        }
+        Ok(())
+    }
+
+    ///Prompt for the password, returns it as a Secret that is wiped from memory once dropped<br>
+    ///With a policy set, the prompt repeats until the password passes<br>
+    ///With confirmation enabled, both entries are compared in constant time and the prompts repeat on a mismatch<br>
+    pub fn get_password(mut self) -> Result<Secret, LibError> {
+        //Puts the terminal back however this function is left, see terminal::TerminalGuard
+        #[cfg(unix)]
+        let _terminal = match self.input_stream.is_terminal() {
+            true => terminal::TerminalGuard::acquire().map_err(LibError::IOError)?,
+            false => None,
+        };
 
+        let mut session = self.start_session()?;
         loop {
-            match self.input_stream.get_token() {
-                Ok(t) => ch = t,
-                Err(LibError::IOError(e)) => {
-                    return Err(LibError::IOError(e));
-                }
-                Err(LibError::UserInterrupt) => match self.ctrl_c_abort {
-                    true => {
-                        return Err(LibError::UserInterrupt);
-                    }
-                    false => {
-                        break;
-                    }
-                },
-                Err(e) => {
-                    return Err(e);
-                }
+            let token = match self.token_timeout(&session) {
+                Some(remaining) => self.input_stream.get_token_timeout(remaining),
+                None => self.input_stream.get_token(),
             };
 
-            match ch {
-                InputToken::Character(c) => {
-                    self.mask
-                        .feed_password(&mut password, c, self.output_stream.as_mut())
-                        .map_err(|e| LibError::PasswordCRUDFailure(e))?;
+            if let Some(password) = self.step(&mut session, token)? {
+                return Ok(password);
+            }
+        }
+    }
+
+    ///Checks the password against the policy, printing the explanation if it does not pass<br>
+    ///Returns true if the user should be asked again, and an error once the attempts run out<br>
+    fn violates(
+        &mut self,
+        policy: Option<&Policy>,
+        password: &Secret,
+        violations: &mut usize,
+    ) -> Result<bool, LibError> {
+        let explanation = match policy.map(|policy| policy.validate(password)) {
+            Some(Err(explanation)) => explanation,
+            _ => return Ok(false),
+        };
+
+        #[cfg(feature = "colored")]
+        let message = colors::paint(&explanation.to_string(), &self.theme.error);
+        #[cfg(not(feature = "colored"))]
+        let message = &explanation;
+
+        writeln!(self.output_stream, "{}", message).map_err(LibError::IOError)?;
+
+        *violations += 1;
+        match policy.is_some_and(|policy| *violations >= policy.attempts) {
+            true => Err(LibError::PolicyViolation(explanation)),
+            false => Ok(true),
+        }
+    }
+
+    ///Compares the password and its confirmation, printing the mismatch message if they differ<br>
+    ///Returns true if the user should be asked again, and an error once the attempts run out<br>
+    fn mismatches(
+        &mut self,
+        confirm: &Confirm,
+        password: &Secret,
+        confirmation: &Secret,
+        mismatches: &mut usize,
+    ) -> Result<bool, LibError> {
+        if password.ct_eq(confirmation) {
+            return Ok(false);
+        }
+
+        #[cfg(feature = "colored")]
+        let message = colors::paint(&confirm.mismatch, &self.theme.mismatch);
+        #[cfg(not(feature = "colored"))]
+        let message = &confirm.mismatch;
+
+        writeln!(self.output_stream, "{}", message).map_err(LibError::IOError)?;
+
+        *mismatches += 1;
+        match *mismatches >= confirm.attempts {
+            true => Err(LibError::ConfirmationFailed(confirm.attempts)),
+            false => Ok(true),
+        }
+    }
+
+    ///Applies one token from the input stream to the password<br>
+    ///Returns true once the entry is finished<br>
+    fn apply_token(
+        &mut self,
+        prompt: &str,
+        password: &mut EditBuffer,
+        token: Result<InputToken, LibError>,
+    ) -> Result<bool, LibError> {
+        let ch = match token {
+            Ok(t) => t,
+            Err(LibError::Timeout) => {
+                writeln!(self.output_stream).map_err(LibError::IOError)?;
+                return Err(LibError::Timeout);
+            }
+            Err(LibError::UserInterrupt) => match self.ctrl_c_abort {
+                true => {
+                    return Err(LibError::UserInterrupt);
                 }
-                InputToken::Backspace => {
-                    self.mask
-                        .pop_password(&mut password, self.output_stream.as_mut())
-                        .map_err(|e| LibError::PasswordCRUDFailure(e))?;
+                false => {
+                    return Ok(true);
                 }
-                InputToken::EOF => {
+            },
+            Err(e) => {
+                return Err(e);
+            }
+        };
+
+        let output_stream = self.output_stream.as_mut();
+
+        match ch {
+            InputToken::Character(c) => {
+                self.mask
+                    .feed_password(password, c, output_stream)
+                    .map_err(LibError::PasswordCRUDFailure)?;
+            }
+            InputToken::Backspace => {
+                self.mask
+                    .pop_password(password, output_stream)
+                    .map_err(LibError::PasswordCRUDFailure)?;
+            }
+            InputToken::Delete => {
+                self.mask
+                    .delete_password(password, output_stream)
+                    .map_err(LibError::PasswordCRUDFailure)?;
+            }
+            InputToken::Left
+            | InputToken::Right
+            | InputToken::Home
+            | InputToken::End
+            | InputToken::WordLeft
+            | InputToken::WordRight
+            | InputToken::Alt(_) => {
+                if let Some(motion) = ch.motion() {
                     self.mask
-                        .end_password(&mut self.output_stream)
-                        .map_err(|e| LibError::PasswordCRUDFailure(e))?;
-                    break;
+                        .move_cursor(password, motion, output_stream)
+                        .map_err(LibError::PasswordCRUDFailure)?;
                 }
-                InputToken::IgnoreToken => {}
             }
+            InputToken::EOF => {
+                self.mask
+                    .end_password(password, output_stream)
+                    .map_err(LibError::PasswordCRUDFailure)?;
+                return Ok(true);
+            }
+            InputToken::Paste(text) => {
+                //Wiped as soon as it has been copied into the password
+                let text = Secret::from(text);
+
+                match self.paste.apply(text.expose()) {
+                    Ok(text) => self
+                        .mask
+                        .paste_password(password, text, output_stream)
+                        .map_err(LibError::PasswordCRUDFailure)?,
+                    Err(message) => {
+                        #[cfg(feature = "colored")]
+                        let message = colors::paint(message, &self.theme.error);
+
+                        //The prompt is drawn again below the message, with what was typed so far
+                        writeln!(output_stream, "\n{}", message).map_err(LibError::IOError)?;
+                        self.prompt_print(prompt).map_err(LibError::IOError)?;
+                        self.mask
+                            .redraw_password(password, self.output_stream.as_mut())
+                            .map_err(LibError::PasswordCRUDFailure)?;
+                    }
+                }
+            }
+            InputToken::Redraw => {
+                //Start over on the current row, the width may have changed
+                write!(output_stream, "\r\x1b[K").map_err(LibError::IOError)?;
+                password.set_view_width(self.view_width(prompt));
+                self.prompt_print(prompt).map_err(LibError::IOError)?;
+                self.mask
+                    .redraw_password(password, self.output_stream.as_mut())
+                    .map_err(LibError::PasswordCRUDFailure)?;
+            }
+            InputToken::Function(_) | InputToken::IgnoreToken => {}
         }
 
-        Ok(password)
+        Ok(false)
     }
 }
 
@@ -1,84 +1,98 @@
-//!Defines basic masks for the password input<br> 
-//!Standard, Blind and Echo are supported as default masks<br> 
-
-use crate::Mask;
+//!Defines basic masks for the password input<br>
+//!Standard, Blind and Echo are supported as default masks<br>
+//!All of them are render based, see the render module<br>
+//!They parse from and display as mask specs (ex: `standard(#)`), see the spec module<br>
+
+use crate::render::{display_width, Glyph, Line, RenderMask};
+use crate::spec::{self, FromSpec, MaskSpec, SpecError};
+use crate::EditBuffer;
+use std::fmt::{self, Display};
+use std::str::FromStr;
 use std::sync::Arc;
 
 #[cfg(feature = "colored")]
 use crate::colors;
-#[cfg(feature = "colored")]
-use colored::Colorize;
 
-pub const DEFAULT_MASK_CHAR: &'static str = "*";
+pub const DEFAULT_MASK_CHAR: &str = "*";
 
 ///Standard Mask <br>
 ///This consists of an internal mask string, say "#", and will cover your characters with said mask, ex: Hello will be masked to #####<br>
 pub struct Standard {
+    mask: Arc<str>,
+    width: usize,
+
     #[cfg(feature = "colored")]
-    mask: colored::ColoredString,
+    color: Arc<str>,
+}
 
-    #[cfg(not(feature = "colored"))]
-    mask: Arc<str>,
+impl Standard {
+    pub fn new(mask: &str) -> Self {
+        Self {
+            mask: Arc::from(mask),
+            width: display_width(mask),
 
-    spaces: Arc<str>,
-    backs: Arc<str>,
+            #[cfg(feature = "colored")]
+            color: Arc::from(colors::DEFAULT_MASK_COLOR),
+        }
+    }
 }
 
-impl Mask for Standard {
-    fn default() -> Self {
+impl RenderMask for Standard {
+    fn default_mask() -> Self {
         Self::new(DEFAULT_MASK_CHAR)
     }
 
-    fn feed_password(
-        &self,
-        password: &mut String,
-        ch: char,
-        o: &mut dyn std::io::Write,
-    ) -> Result<(), String> {
-        // o.write(self.mask.as_bytes()).map_err(|e| e.to_string())?;
-        write!(o , "{}" , self.mask).map_err(|e| e.to_string())?;
-        password.push(ch);
-        Ok(())
-    }
-
-    fn pop_password(
-        &self,
-        password: &mut String,
-        o: &mut dyn std::io::Write,
-    ) -> Result<(), String> {
-        match password.pop() {
-            Some(_) => {
-                let buffer = format!(
-                    "{backs}{spaces}{backs}",
-                    backs = &self.backs,
-                    spaces = &self.spaces
-                );
-                write!(o , "{}" , buffer).map_err(|e| e.to_string())?;
-                Ok(())
-            }
-            None => Ok(()),
-        }
+    fn render(&self, password: &EditBuffer, _finished: bool) -> Line {
+        #[cfg(feature = "colored")]
+        let mask = colors::paint(&self.mask, &self.color);
+        #[cfg(not(feature = "colored"))]
+        let mask = &self.mask;
+
+        Line::new(
+            vec![Glyph::new(mask, self.width); password.len()],
+            password.cursor(),
+        )
     }
 
     #[cfg(feature = "colored")]
-    fn set_color(&mut self, c: &str) -> () {
-        self.mask = self.mask.clone().color(c);
+    fn set_color(&mut self, c: &str) {
+        self.color = Arc::from(c);
     }
 }
 
-impl Standard {
-    pub fn new(mask: &str) -> Self {
-        #[cfg(feature = "colored")]
-        let mask = colored::ColoredString::from(mask).color(colors::DEFAULT_MASK_COLOR);
+///`standard`, `standard(<mask>)`, with a `color` option<br>
+impl FromSpec for Standard {
+    const NAMES: &'static [&'static str] = &["standard"];
+    const ARGS: &'static [&'static str] = &["mask"];
+    const OPTIONS: &'static [&'static str] = &["color"];
+    const DESCRIPTION: &'static str = "Every character covered by the mask string";
+
+    fn from_spec(spec: &MaskSpec) -> Result<Self, SpecError> {
+        let mut mask = match spec.positional(0) {
+            Some(arg) => Self::new(&arg.value),
+            None => Self::default_mask(),
+        };
+        spec::apply_color(spec, &mut mask)?;
+        Ok(mask)
+    }
+}
 
-        #[cfg(not(feature = "colored"))]
-        let mask: Arc<str> = Arc::from(mask);
+impl FromStr for Standard {
+    type Err = SpecError;
 
-        Self {
-            spaces: Arc::from(" ".repeat(mask.len())),
-            backs: Arc::from("\x08".repeat(mask.len())),
-            mask,
-        }
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        spec::parse(s)
+    }
+}
+
+impl Display for Standard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let spec = MaskSpec::new(Self::NAMES[0]).arg(&self.mask);
+
+        #[cfg(feature = "colored")]
+        let spec = spec.color(&self.color);
+
+        spec.fmt(f)
     }
 }
 
@@ -91,50 +105,65 @@ pub struct Echo {
     color: Arc<str>,
 }
 
-impl Mask for Echo {
-    fn default() -> Self {
+impl RenderMask for Echo {
+    fn default_mask() -> Self {
         Self {
             #[cfg(feature = "colored")]
             color: Arc::from(colors::DEFAULT_MASK_COLOR),
         }
     }
 
-    fn feed_password(
-        &self,
-        password: &mut String,
-        ch: char,
-        o: &mut dyn std::io::Write,
-    ) -> Result<(), String> {
-        let chs = ch.to_string();
+    fn render(&self, password: &EditBuffer, _finished: bool) -> Line {
+        let glyphs = password
+            .graphemes()
+            .map(|g| {
+                #[cfg(feature = "colored")]
+                let text = colors::paint(g, &self.color);
+                #[cfg(not(feature = "colored"))]
+                let text = g.to_string();
 
-        #[cfg(feature = "colored")]
-        let chs = chs.color(self.color.as_ref());
-
-        // o.write(chs.as_bytes()).map_err(|e| e.to_string())?;
-        
-        write!(o, "{}", chs).map_err(|e| e.to_string())?;
-        password.push(ch);
-        Ok(())
-    }
-
-    fn pop_password(
-        &self,
-        password: &mut String,
-        o: &mut dyn std::io::Write,
-    ) -> Result<(), String> {
-        match password.pop() {
-            Some(_) => {
-                o.write(b"\x08 \x08").map_err(|e| e.to_string())?;
-                Ok(())
-            }
-            None => Ok(()),
-        }
+                Glyph::cleartext(text, display_width(g))
+            })
+            .collect();
+
+        Line::new(glyphs, password.cursor())
     }
 
     #[cfg(feature = "colored")]
-    fn set_color(&mut self, c: &str) -> () {
+    fn set_color(&mut self, c: &str) {
         self.color = Arc::from(c);
-        // Ok(())
+    }
+}
+
+///`echo`, with a `color` option<br>
+impl FromSpec for Echo {
+    const NAMES: &'static [&'static str] = &["echo"];
+    const OPTIONS: &'static [&'static str] = &["color"];
+    const DESCRIPTION: &'static str = "The password in cleartext";
+
+    fn from_spec(spec: &MaskSpec) -> Result<Self, SpecError> {
+        let mut mask = Self::default_mask();
+        spec::apply_color(spec, &mut mask)?;
+        Ok(mask)
+    }
+}
+
+impl FromStr for Echo {
+    type Err = SpecError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        spec::parse(s)
+    }
+}
+
+impl Display for Echo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let spec = MaskSpec::new(Self::NAMES[0]);
+
+        #[cfg(feature = "colored")]
+        let spec = spec.color(&self.color);
+
+        spec.fmt(f)
     }
 }
 
@@ -144,35 +173,39 @@ impl Mask for Echo {
 ///This is similar to setting the lib::Void as the output stream<br>
 pub struct Blind;
 
-impl Mask for Blind {
-    fn default() -> Self {
+impl RenderMask for Blind {
+    fn default_mask() -> Self {
         Default::default()
     }
 
-    fn end_password(&self, o: &mut dyn std::io::Write) -> Result<(), String> {
-        writeln!(o , "").map_err(|e| e.to_string())?;
-        Ok(())
+    fn render(&self, _password: &EditBuffer, _finished: bool) -> Line {
+        Line::default()
     }
 
-    fn feed_password(
-        &self,
-        password: &mut String,
-        ch: char,
-        _o: &mut dyn std::io::Write,
-    ) -> Result<(), String> {
-        password.push(ch);
-        Ok(())
+    #[cfg(feature = "colored")]
+    fn set_color(&mut self, _color: &str) {}
+}
+
+///`blind`, no arguments<br>
+impl FromSpec for Blind {
+    const NAMES: &'static [&'static str] = &["blind"];
+    const DESCRIPTION: &'static str = "Nothing is shown, like bash read";
+
+    fn from_spec(_spec: &MaskSpec) -> Result<Self, SpecError> {
+        Ok(Blind)
     }
+}
+
+impl FromStr for Blind {
+    type Err = SpecError;
 
-    fn pop_password(
-        &self,
-        password: &mut String,
-        _o: &mut dyn std::io::Write,
-    ) -> Result<(), String> {
-        let _ = password.pop();
-        Ok(())
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        spec::parse(s)
     }
+}
 
-    #[cfg(feature = "colored")]
-    fn set_color(&mut self, _color: &str){}
+impl Display for Blind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        MaskSpec::new(Self::NAMES[0]).fmt(f)
+    }
 }
@@ -0,0 +1,56 @@
+//!What to do with text pasted into the prompt<br>
+//!Pastes arrive as a single InputToken::Paste when the terminal supports bracketed paste, so they can be told apart from typing<br>
+
+use std::str::FromStr;
+
+pub const DEFAULT_PASTE_REJECT_MESSAGE: &str = "Pasting is not allowed, please type the password";
+
+///Handling of pasted text, see GPass::set_paste_policy<br>
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum PastePolicy {
+    ///Insert the pasted text exactly as it is<br>
+    Allow,
+
+    ///Insert the pasted text without its trailing newline, if any (copying a whole line usually picks one up)<br>
+    #[default]
+    StripNewline,
+
+    ///Drop the paste after showing the message, what was typed before it is kept<br>
+    Reject(String),
+}
+
+impl PastePolicy {
+    ///The text to insert for a paste, or the message to show if the paste is rejected<br>
+    pub fn apply<'a>(&'a self, text: &'a str) -> Result<&'a str, &'a str> {
+        match self {
+            PastePolicy::Allow => Ok(text),
+            PastePolicy::StripNewline => Ok(text
+                .strip_suffix('\n')
+                .map(|text| text.strip_suffix('\r').unwrap_or(text))
+                .or_else(|| text.strip_suffix('\r'))
+                .unwrap_or(text)),
+            PastePolicy::Reject(message) => Err(message),
+        }
+    }
+}
+
+///Parses `allow`, `strip` or `reject`, optionally followed by the rejection message: `reject:No pasting here`<br>
+impl FromStr for PastePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, message) = match s.split_once(':') {
+            Some((name, message)) => (name, Some(message)),
+            None => (s, None),
+        };
+
+        match (name.trim().to_lowercase().as_str(), message) {
+            ("allow", None) => Ok(PastePolicy::Allow),
+            ("strip", None) => Ok(PastePolicy::StripNewline),
+            ("reject", message) => Ok(PastePolicy::Reject(
+                message.unwrap_or(DEFAULT_PASTE_REJECT_MESSAGE).to_string(),
+            )),
+            _ => Err(format!("Unknown paste policy {:?}", s)),
+        }
+    }
+}
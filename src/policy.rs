@@ -0,0 +1,230 @@
+//!Password policies, checked once the password has been entered<br>
+//!A Policy is a list of validators (length limits, required character classes, banned substrings, or any closure)<br>
+//!When the password does not pass, GPass shows the explanation and asks again, up to `attempts` times<br>
+//!Policies can also be written as a small spec, ex: `min=12, max=64, require=upper+lower+digit, ban=password:qwerty, attempts=3`<br>
+
+use crate::Secret;
+use std::str::FromStr;
+use unicode_segmentation::UnicodeSegmentation;
+
+///Trait for password validators<br>
+///Returns Ok if the password passes, else an explanation to show to the user<br>
+pub trait Validator {
+    fn validate(&self, password: &Secret) -> Result<(), String>;
+}
+
+///Any closure over the cleartext password works as a validator<br>
+impl<F: Fn(&str) -> Result<(), String>> Validator for F {
+    fn validate(&self, password: &Secret) -> Result<(), String> {
+        self(password.expose())
+    }
+}
+
+///Minimum number of characters, counted as graphemes like the rest of the prompt (`é` written as `e` and an accent is one)<br>
+pub struct MinLength(pub usize);
+
+impl Validator for MinLength {
+    fn validate(&self, password: &Secret) -> Result<(), String> {
+        match password.expose().graphemes(true).count() >= self.0 {
+            true => Ok(()),
+            false => Err(format!("Password must be at least {} characters long", self.0)),
+        }
+    }
+}
+
+///Maximum number of characters, counted as graphemes<br>
+pub struct MaxLength(pub usize);
+
+impl Validator for MaxLength {
+    fn validate(&self, password: &Secret) -> Result<(), String> {
+        match password.expose().graphemes(true).count() <= self.0 {
+            true => Ok(()),
+            false => Err(format!("Password must be at most {} characters long", self.0)),
+        }
+    }
+}
+
+///Character classes for Require<br>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharClass {
+    Lower,
+    Upper,
+    Digit,
+    ///Anything that is not a letter, digit or whitespace<br>
+    Symbol,
+}
+
+impl CharClass {
+    pub fn matches(&self, ch: char) -> bool {
+        match self {
+            CharClass::Lower => ch.is_lowercase(),
+            CharClass::Upper => ch.is_uppercase(),
+            CharClass::Digit => ch.is_numeric(),
+            CharClass::Symbol => !ch.is_alphanumeric() && !ch.is_whitespace(),
+        }
+    }
+
+    fn describe(&self) -> &'static str {
+        match self {
+            CharClass::Lower => "a lowercase letter",
+            CharClass::Upper => "an uppercase letter",
+            CharClass::Digit => "a digit",
+            CharClass::Symbol => "a symbol",
+        }
+    }
+}
+
+impl FromStr for CharClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "lower" | "lowercase" => Ok(CharClass::Lower),
+            "upper" | "uppercase" => Ok(CharClass::Upper),
+            "digit" | "digits" | "number" => Ok(CharClass::Digit),
+            "symbol" | "symbols" | "special" => Ok(CharClass::Symbol),
+            other => Err(format!("Unknown character class {:?}", other)),
+        }
+    }
+}
+
+///At least one character of the class<br>
+pub struct Require(pub CharClass);
+
+impl Validator for Require {
+    fn validate(&self, password: &Secret) -> Result<(), String> {
+        match password.expose().chars().any(|ch| self.0.matches(ch)) {
+            true => Ok(()),
+            false => Err(format!("Password must contain {}", self.0.describe())),
+        }
+    }
+}
+
+///None of the substrings may appear in the password, compared case insensitively<br>
+pub struct Banned(pub Vec<String>);
+
+impl Validator for Banned {
+    fn validate(&self, password: &Secret) -> Result<(), String> {
+        let lowered = Secret::from(password.expose().to_lowercase());
+
+        match self
+            .0
+            .iter()
+            .find(|banned| lowered.expose().contains(&banned.to_lowercase()))
+        {
+            Some(banned) => Err(format!("Password must not contain {:?}", banned)),
+            None => Ok(()),
+        }
+    }
+}
+
+pub const DEFAULT_POLICY_ATTEMPTS: usize = 3;
+
+///A set of validators along with the number of tries the user gets<br>
+pub struct Policy {
+    validators: Vec<Box<dyn Validator>>,
+
+    ///Number of tries before giving up with LibError::PolicyViolation<br>
+    pub attempts: usize,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self::new(DEFAULT_POLICY_ATTEMPTS)
+    }
+}
+
+impl Policy {
+    pub fn new(attempts: usize) -> Self {
+        Self {
+            validators: Vec::new(),
+            attempts,
+        }
+    }
+
+    ///Add a validator to the policy<br>
+    pub fn with(mut self, validator: impl Validator + 'static) -> Self {
+        self.validators.push(Box::new(validator));
+        self
+    }
+
+    ///Read a policy spec from a file, one or more rules per line, `#` starts a comment<br>
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path.as_ref()).map_err(|e| e.to_string())?;
+        Self::from_lines(&contents)
+    }
+
+    ///A policy spec laid out as in a policy file (see from_file)<br>
+    pub fn from_lines(contents: &str) -> Result<Self, String> {
+        contents
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join(",")
+            .parse()
+    }
+}
+
+///Checks every validator, the explanations of all failing ones are joined together<br>
+impl Validator for Policy {
+    fn validate(&self, password: &Secret) -> Result<(), String> {
+        let failures: Vec<String> = self
+            .validators
+            .iter()
+            .filter_map(|v| v.validate(password).err())
+            .collect();
+
+        match failures.is_empty() {
+            true => Ok(()),
+            false => Err(failures.join("\n")),
+        }
+    }
+}
+
+///Parses a comma separated policy spec: `min=N`, `max=N`, `require=class+class`, `ban=word:word`, `attempts=N`<br>
+impl FromStr for Policy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut policy = Policy::default();
+
+        for rule in s.split(',').map(str::trim).filter(|rule| !rule.is_empty()) {
+            let (key, value) = rule
+                .split_once('=')
+                .ok_or_else(|| format!("Policy rule {:?} is not of the form key=value", rule))?;
+            let value = value.trim();
+            let number = || {
+                value
+                    .parse::<usize>()
+                    .map_err(|e| format!("Policy rule {:?}: {}", rule, e))
+            };
+
+            policy = match key.trim().to_lowercase().as_str() {
+                "min" => policy.with(MinLength(number()?)),
+                "max" => policy.with(MaxLength(number()?)),
+                "attempts" => Policy {
+                    attempts: number()?,
+                    ..policy
+                },
+                "require" => {
+                    for class in value.split('+') {
+                        policy = policy.with(Require(class.parse()?));
+                    }
+                    policy
+                }
+                //Empty entries (`ban=`, `ban=a::b`) would be found in every password
+                "ban" => policy.with(Banned(
+                    value
+                        .split(':')
+                        .map(str::trim)
+                        .filter(|word| !word.is_empty())
+                        .map(String::from)
+                        .collect(),
+                )),
+                other => return Err(format!("Unknown policy rule {:?}", other)),
+            };
+        }
+
+        Ok(policy)
+    }
+}
@@ -0,0 +1,385 @@
+//!End-to-end harness running GPass against a pseudo-terminal, for testing the Getch input path (raw mode, bracketed paste, signals) on Linux<br>
+//!Getch always reads the terminal on stdin, so the prompt runs in a child process whose controlling terminal is the pty, and the test talks to it through the master side<br>
+//!The child is usually the test binary itself, re-run on a single test (see Pty::test) that checks is_child and reports the outcome of the prompt with report<br>
+//!Ctrl-Z only stops a child spawned as a job (Pty::test_job), the way a shell with job control runs it<br>
+//!Functionality only available by enabling feature flag `pty`<br>
+//!```rust,ignore
+//!#[test]
+//!fn prompt() -> std::io::Result<()> {
+//!    if gpass::pty::is_child() {
+//!        gpass::pty::report(&GPass::default().get_password());
+//!        return Ok(());
+//!    }
+//!
+//!    let mut pty = gpass::pty::Pty::test("prompt")?;
+//!    pty.expect("Enter the Password")?;
+//!    pty.send("hel\x7flo\x1b[DX\r")?;
+//!    assert_eq!(pty.result()?, r#"Ok("helXo")"#);
+//!    Ok(())
+//!}
+//!```
+
+use crate::testing::Screen;
+use crate::{LibError, Secret};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+
+///Environment variable set for the child process<br>
+pub const CHILD_VAR: &str = "GPASS_PTY_CHILD";
+///Start of the line report prints, followed by the result<br>
+pub const RESULT_PREFIX: &str = "gpass-pty-result: ";
+///How long Pty waits for output or for the child to exit by default<br>
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+///Size of the terminal on spawn, rows then columns<br>
+pub const DEFAULT_SIZE: (u16, u16) = (24, 80);
+
+///Whether this process was spawned by a Pty<br>
+pub fn is_child() -> bool {
+    std::env::var_os(CHILD_VAR).is_some()
+}
+
+///Prints the result of a prompt on stdout for Pty::result to pick up, ex: `Ok("hunter2")` or `Err(UserInterrupt)`<br>
+pub fn report(result: &Result<Secret, LibError>) {
+    println!(
+        "{}{:?}",
+        RESULT_PREFIX,
+        result.as_ref().map(|secret| secret.expose())
+    );
+}
+
+fn check(ret: libc::c_int) -> std::io::Result<libc::c_int> {
+    match ret {
+        -1 => Err(std::io::Error::last_os_error()),
+        ret => Ok(ret),
+    }
+}
+
+fn timed_out(what: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::TimedOut,
+        format!("timed out waiting for {}", what),
+    )
+}
+
+///Opens a new pty pair, master then slave<br>
+fn open() -> std::io::Result<(File, OwnedFd)> {
+    unsafe {
+        let master = check(libc::posix_openpt(
+            libc::O_RDWR | libc::O_NOCTTY | libc::O_CLOEXEC,
+        ))?;
+        let master = File::from_raw_fd(master);
+        check(libc::grantpt(master.as_raw_fd()))?;
+        check(libc::unlockpt(master.as_raw_fd()))?;
+
+        let mut name = [0 as libc::c_char; 128];
+        match libc::ptsname_r(master.as_raw_fd(), name.as_mut_ptr(), name.len()) {
+            0 => {}
+            e => return Err(std::io::Error::from_raw_os_error(e)),
+        }
+
+        let slave = check(libc::open(
+            name.as_ptr(),
+            libc::O_RDWR | libc::O_NOCTTY | libc::O_CLOEXEC,
+        ))?;
+        Ok((master, OwnedFd::from_raw_fd(slave)))
+    }
+}
+
+///Forks the job off in a process group of its own, puts it in the foreground and waits for it as the session leader, the way a shell with job control does<br>
+///Only the job returns (to go on and exec the command), the leader exits the way the job does<br>
+///Runs between fork and exec, so only async-signal-safe calls<br>
+unsafe fn lead() -> std::io::Result<()> {
+    let job = check(libc::fork())?;
+    if job == 0 {
+        check(libc::setpgid(0, 0))?;
+        //Taking the terminal from a background process group would stop the job with SIGTTOU
+        libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+        check(libc::tcsetpgrp(libc::STDIN_FILENO, libc::getpid()))?;
+        libc::signal(libc::SIGTTOU, libc::SIG_DFL);
+        return Ok(());
+    }
+
+    //Spawn waits on a pipe for the exec, which the leader would otherwise hold open until the job is done
+    if libc::syscall(libc::SYS_close_range, 3, libc::c_uint::MAX, 0) != 0 {
+        for fd in 3..1024 {
+            libc::close(fd);
+        }
+    }
+
+    let mut status = 0;
+    while libc::waitpid(job, &mut status, 0) == -1 {
+        if *libc::__errno_location() != libc::EINTR {
+            libc::_exit(1);
+        }
+    }
+    if libc::WIFSIGNALED(status) {
+        libc::signal(libc::WTERMSIG(status), libc::SIG_DFL);
+        libc::kill(libc::getpid(), libc::WTERMSIG(status));
+    }
+    libc::_exit(libc::WEXITSTATUS(status))
+}
+
+///A child process running on a pseudo-terminal<br>
+///Everything the child writes to the terminal is kept, both as raw bytes (output) and drawn on a testing::Screen (screen)<br>
+///The child is killed when the Pty is dropped<br>
+pub struct Pty {
+    master: File,
+    child: Child,
+    output: Vec<u8>,
+    ///How far expect has read into output<br>
+    seen: usize,
+    screen: Screen,
+    timeout: Duration,
+    ///The child closed its side of the terminal<br>
+    closed: bool,
+}
+
+impl Pty {
+    ///Spawns `command` with the slave side as its stdin, stdout, stderr and controlling terminal, in a session of its own<br>
+    pub fn spawn(command: Command) -> std::io::Result<Self> {
+        Self::start(command, false)
+    }
+
+    ///Like spawn, but the command runs as a job under a session leader that waits for it, as it would from a shell<br>
+    ///Needed for Ctrl-Z (SIGTSTP), which does nothing to the process group of a session leader since nothing could resume it<br>
+    pub fn spawn_job(command: Command) -> std::io::Result<Self> {
+        Self::start(command, true)
+    }
+
+    fn start(mut command: Command, job: bool) -> std::io::Result<Self> {
+        let (master, slave) = open()?;
+
+        command
+            .env(CHILD_VAR, "1")
+            .stdin(Stdio::from(slave.try_clone()?))
+            .stdout(Stdio::from(slave.try_clone()?))
+            .stderr(Stdio::from(slave));
+
+        //Only async-signal-safe calls between fork and exec
+        unsafe {
+            command.pre_exec(move || {
+                check(libc::setsid())?;
+                check(libc::ioctl(libc::STDIN_FILENO, libc::TIOCSCTTY, 0))?;
+                if job {
+                    lead()?;
+                }
+                Ok(())
+            });
+        }
+
+        let pty = Pty {
+            child: command.spawn()?,
+            master,
+            output: Vec::new(),
+            seen: 0,
+            screen: Screen::new(),
+            timeout: DEFAULT_TIMEOUT,
+            closed: false,
+        };
+        pty.set_size(DEFAULT_SIZE.0, DEFAULT_SIZE.1)?;
+        Ok(pty)
+    }
+
+    ///The current test binary, to run the test `name` alone with output shown<br>
+    fn test_command(name: &str) -> std::io::Result<Command> {
+        let mut command = Command::new(std::env::current_exe()?);
+        command.args([name, "--exact", "--nocapture", "--quiet", "--test-threads=1"]);
+        Ok(command)
+    }
+
+    ///Re-runs the current test binary on the test `name` alone (the full path, ex: `tests::prompt`), with output shown<br>
+    pub fn test(name: &str) -> std::io::Result<Self> {
+        Self::spawn(Self::test_command(name)?)
+    }
+
+    ///Like test, but as a job (see spawn_job)<br>
+    pub fn test_job(name: &str) -> std::io::Result<Self> {
+        Self::spawn_job(Self::test_command(name)?)
+    }
+
+    ///How long expect, result and wait give the child, DEFAULT_TIMEOUT by default<br>
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    ///Resizes the terminal, which sends the child a SIGWINCH<br>
+    pub fn set_size(&self, rows: u16, columns: u16) -> std::io::Result<()> {
+        let size = libc::winsize {
+            ws_row: rows,
+            ws_col: columns,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        check(unsafe { libc::ioctl(self.master.as_raw_fd(), libc::TIOCSWINSZ, &size) })?;
+        Ok(())
+    }
+
+    ///Types `bytes` on the terminal, ex: `"\x1b[D"` for the left arrow or `"\x03"` for Ctrl-C<br>
+    pub fn send(&mut self, bytes: impl AsRef<[u8]>) -> std::io::Result<()> {
+        self.master.write_all(bytes.as_ref())?;
+        self.master.flush()
+    }
+
+    ///The process group in the foreground of the terminal, the job for spawn_job<br>
+    fn foreground(&self) -> std::io::Result<libc::pid_t> {
+        check(unsafe { libc::tcgetpgrp(self.master.as_raw_fd()) })
+    }
+
+    ///Sends `signal` to the processes in the foreground, ex: SIGCONT to resume them after Ctrl-Z, as `fg` does<br>
+    pub fn signal(&self, signal: libc::c_int) -> std::io::Result<()> {
+        check(unsafe { libc::kill(-self.foreground()?, signal) })?;
+        Ok(())
+    }
+
+    ///Whether the process in the foreground is stopped, ex: by Ctrl-Z<br>
+    pub fn is_stopped(&self) -> std::io::Result<bool> {
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", self.foreground()?))?;
+        //The state comes right after the command name, which is in brackets and may hold anything
+        let state = stat.rsplit_once(") ").and_then(|(_, rest)| rest.chars().next());
+        Ok(matches!(state, Some('T')))
+    }
+
+    ///Reads what is available within `deadline`, false once the child has closed the terminal or the deadline passed<br>
+    fn fill(&mut self, deadline: Instant) -> std::io::Result<bool> {
+        if self.closed {
+            return Ok(false);
+        }
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let mut fd = libc::pollfd {
+            fd: self.master.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let millis = remaining.as_millis().min(i32::MAX as u128) as i32;
+        match unsafe { libc::poll(&mut fd, 1, millis) } {
+            0 => return Ok(false),
+            -1 => {
+                let e = std::io::Error::last_os_error();
+                return match e.kind() {
+                    std::io::ErrorKind::Interrupted => Ok(true),
+                    _ => Err(e),
+                };
+            }
+            _ => {}
+        }
+
+        let mut buf = [0u8; 4096];
+        match self.master.read(&mut buf) {
+            Ok(n) if n > 0 => {
+                self.output.extend_from_slice(&buf[..n]);
+                self.screen.write_all(&buf[..n])?;
+                Ok(true)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => Ok(true),
+            //Linux fails reads on the master with EIO once every slave is closed
+            Ok(_) => {
+                self.closed = true;
+                Ok(false)
+            }
+            Err(e) if e.raw_os_error() == Some(libc::EIO) => {
+                self.closed = true;
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    ///Reads until `text` shows up in the output, and returns the output from the end of the last match up to and including it<br>
+    ///A TimedOut error if it does not within the timeout, or if the child closes the terminal first<br>
+    pub fn expect(&mut self, text: &str) -> std::io::Result<String> {
+        let deadline = Instant::now() + self.timeout;
+
+        loop {
+            let found = self.output[self.seen..]
+                .windows(text.len().max(1))
+                .position(|window| window == text.as_bytes());
+
+            if let Some(at) = found {
+                let end = self.seen + at + text.len();
+                let matched = String::from_utf8_lossy(&self.output[self.seen..end]).into_owned();
+                self.seen = end;
+                return Ok(matched);
+            }
+
+            if !self.fill(deadline)? && (self.closed || Instant::now() >= deadline) {
+                return Err(timed_out(&format!("{:?}", text)));
+            }
+        }
+    }
+
+    ///Reads until the line the cursor is on, as drawn on the screen, is `line` (without trailing blanks)<br>
+    ///Unlike expect this sees through colors and redraws, ex: `Enter the Password: ***`<br>
+    pub fn expect_line(&mut self, line: &str) -> std::io::Result<()> {
+        let deadline = Instant::now() + self.timeout;
+
+        while self.screen.line() != line {
+            if !self.fill(deadline)? && (self.closed || Instant::now() >= deadline) {
+                return Err(timed_out(&format!("the line {:?}, got {:?}", line, self.screen.line())));
+            }
+        }
+        Ok(())
+    }
+
+    ///Waits for the line printed by report, and returns the result in it<br>
+    pub fn result(&mut self) -> std::io::Result<String> {
+        self.expect(RESULT_PREFIX)?;
+        let line = self.expect("\n")?;
+        Ok(line.trim_end().to_string())
+    }
+
+    ///Reads the output until the child closes the terminal, then waits for it to exit<br>
+    pub fn wait(&mut self) -> std::io::Result<ExitStatus> {
+        let deadline = Instant::now() + self.timeout;
+
+        while !self.closed && Instant::now() < deadline {
+            self.fill(deadline)?;
+        }
+
+        loop {
+            if let Some(status) = self.child.try_wait()? {
+                return Ok(status);
+            }
+            if Instant::now() >= deadline {
+                return Err(timed_out("the child to exit"));
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    ///Everything the child has written so far, as text<br>
+    pub fn output(&self) -> String {
+        String::from_utf8_lossy(&self.output).into_owned()
+    }
+
+    ///The output so far, as shown on a terminal<br>
+    pub fn screen(&self) -> &Screen {
+        &self.screen
+    }
+
+    ///The current settings of the terminal, as the child left them<br>
+    pub fn termios(&self) -> std::io::Result<libc::termios> {
+        let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+        check(unsafe { libc::tcgetattr(self.master.as_raw_fd(), &mut termios) })?;
+        Ok(termios)
+    }
+
+    ///Whether the terminal has echo and line buffering switched off, as while GPass holds it<br>
+    pub fn is_raw(&self) -> std::io::Result<bool> {
+        let termios = self.termios()?;
+        Ok(termios.c_lflag & (libc::ECHO | libc::ICANON) == 0)
+    }
+}
+
+impl Drop for Pty {
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
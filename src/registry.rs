@@ -0,0 +1,227 @@
+//!Registry of masks by name, used to build masks from specs (see the spec module)<br>
+//!Applications register their own masks next to the built-in ones, and env_mask!, env::mask_from_str and `gpassx --mask` pick them up by name<br>
+
+use crate::spec::{FromSpec, MaskSpec, SpecError};
+use crate::Mask;
+use std::fmt;
+use std::sync::{Arc, PoisonError, RwLock};
+
+///Builds a mask from a spec whose arguments have already been checked against the schema<br>
+pub type MaskFactory = Arc<dyn Fn(&MaskSpec) -> Result<Box<dyn Mask>, SpecError> + Send + Sync>;
+
+///Arguments a registered mask takes<br>
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Schema {
+    ///Names of the positional arguments, all of them optional<br>
+    pub args: Vec<String>,
+    ///Keys of the `key=value` options<br>
+    pub options: Vec<String>,
+}
+
+impl Schema {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn arg(mut self, name: &str) -> Self {
+        self.args.push(name.to_string());
+        self
+    }
+
+    pub fn option(mut self, key: &str) -> Self {
+        self.options.push(key.to_string());
+        self
+    }
+}
+
+///A registered mask<br>
+#[derive(Clone)]
+pub struct MaskInfo {
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub description: String,
+    pub schema: Schema,
+    factory: MaskFactory,
+}
+
+impl MaskInfo {
+    ///Whether `name` is the name or one of the aliases of the mask (case insensitive)<br>
+    pub fn answers_to(&self, name: &str) -> bool {
+        std::iter::once(&self.name)
+            .chain(&self.aliases)
+            .any(|n| n.eq_ignore_ascii_case(name))
+    }
+
+    ///How the mask is written, ex: `standard(mask, color=...)`<br>
+    pub fn usage(&self) -> String {
+        let args = self
+            .schema
+            .args
+            .iter()
+            .cloned()
+            .chain(self.schema.options.iter().map(|key| format!("{}=...", key)))
+            .collect::<Vec<_>>();
+
+        match args.is_empty() {
+            true => self.name.clone(),
+            false => format!("{}({})", self.name, args.join(", ")),
+        }
+    }
+
+    ///Builds the mask, checking the arguments of the spec first<br>
+    pub fn build(&self, spec: &MaskSpec) -> Result<Box<dyn Mask>, SpecError> {
+        let options: Vec<&str> = self.schema.options.iter().map(String::as_str).collect();
+        spec.expect(self.schema.args.len(), &options)?;
+        (self.factory)(spec)
+    }
+}
+
+///One line per mask for help output: usage, aliases and description<br>
+impl fmt::Display for MaskInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.usage())?;
+        if !self.aliases.is_empty() {
+            write!(f, " (also {})", self.aliases.join(", "))?;
+        }
+        if !self.description.is_empty() {
+            write!(f, ": {}", self.description)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for MaskInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MaskInfo")
+            .field("name", &self.name)
+            .field("aliases", &self.aliases)
+            .field("description", &self.description)
+            .field("schema", &self.schema)
+            .finish()
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref GLOBAL: RwLock<MaskRegistry> = RwLock::new(MaskRegistry::with_defaults());
+}
+
+///Named mask factories, with aliases, argument schemas and descriptions<br>
+///Masks are looked up by name or alias, case insensitive. Registering a name that is already taken replaces the earlier mask for that name<br>
+#[derive(Clone, Debug, Default)]
+pub struct MaskRegistry {
+    masks: Vec<MaskInfo>,
+}
+
+impl MaskRegistry {
+    ///An empty registry<br>
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    ///A registry with the built-in masks (those of the enabled features)<br>
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry
+            .register_spec::<crate::masks::Standard>()
+            .register_spec::<crate::masks::Echo>()
+            .register_spec::<crate::masks::Blind>();
+
+        #[cfg(feature = "reverse")]
+        registry
+            .register_spec::<crate::reverse::OneReverse>()
+            .register_spec::<crate::reverse::MimiReverse>();
+
+        #[cfg(feature = "strength")]
+        registry.register_spec::<crate::strength::StrengthMeter>();
+
+        registry
+    }
+
+    ///The registry used by env_mask!, env::mask_from_str and gpassx, starting out with the built-in masks<br>
+    ///Ex: `MaskRegistry::global().write().unwrap().register_spec::<MyMask>();`<br>
+    pub fn global() -> &'static RwLock<MaskRegistry> {
+        &GLOBAL
+    }
+
+    ///Builds a mask from a spec string using the global registry<br>
+    pub fn resolve_global(spec: &str) -> Result<Box<dyn Mask>, SpecError> {
+        Self::global()
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .resolve(spec)
+    }
+
+    ///Register a mask under `name` and `aliases`<br>
+    pub fn register(
+        &mut self,
+        name: &str,
+        aliases: &[&str],
+        description: &str,
+        schema: Schema,
+        factory: impl Fn(&MaskSpec) -> Result<Box<dyn Mask>, SpecError> + Send + Sync + 'static,
+    ) -> &mut Self {
+        let info = MaskInfo {
+            name: name.to_string(),
+            aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+            description: description.to_string(),
+            schema,
+            factory: Arc::new(factory),
+        };
+
+        //Names taken over by the new mask are dropped from the earlier ones
+        for mask in &mut self.masks {
+            mask.aliases.retain(|alias| !info.answers_to(alias));
+        }
+        let at = self
+            .masks
+            .iter()
+            .position(|mask| info.answers_to(&mask.name))
+            .unwrap_or(self.masks.len());
+        self.masks.retain(|mask| !info.answers_to(&mask.name));
+        self.masks.insert(at, info);
+
+        self
+    }
+
+    ///Register a mask implementing spec::FromSpec, under its names and with its schema and description<br>
+    pub fn register_spec<M: FromSpec + Mask + 'static>(&mut self) -> &mut Self {
+        let schema = Schema {
+            args: M::ARGS.iter().map(|arg| arg.to_string()).collect(),
+            options: M::OPTIONS.iter().map(|key| key.to_string()).collect(),
+        };
+
+        self.register(
+            M::NAMES[0],
+            &M::NAMES[1..],
+            M::DESCRIPTION,
+            schema,
+            |spec| M::from_spec(spec).map(|mask| Box::new(mask) as Box<dyn Mask>),
+        )
+    }
+
+    ///The mask registered under `name` or as an alias<br>
+    pub fn get(&self, name: &str) -> Option<&MaskInfo> {
+        self.masks.iter().find(|mask| mask.answers_to(name))
+    }
+
+    ///Every registered mask, in the order they were registered<br>
+    pub fn masks(&self) -> impl Iterator<Item = &MaskInfo> {
+        self.masks.iter()
+    }
+
+    ///Builds the mask for a parsed spec<br>
+    pub fn build(&self, spec: &MaskSpec) -> Result<Box<dyn Mask>, SpecError> {
+        match self.get(&spec.name) {
+            Some(mask) => mask.build(spec),
+            None => Err(SpecError::new(
+                spec.position,
+                format!("unknown mask {:?}", spec.name),
+            )),
+        }
+    }
+
+    ///Parses a spec and builds its mask, ex: `registry.resolve("or(#)")`<br>
+    pub fn resolve(&self, spec: &str) -> Result<Box<dyn Mask>, SpecError> {
+        self.build(&spec.parse()?)
+    }
+}
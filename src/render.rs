@@ -0,0 +1,296 @@
+//!Render based masks<br>
+//!A RenderMask only describes what the input line should look like for the current password, as a Line of glyphs with a cursor<br>
+//!The shared renderer (`draw`) compares that with what is on screen and rewrites only what changed, so masks never deal with backspaces or cell counts themselves<br>
+//!Every RenderMask is a Mask through a blanket implementation, masks written directly against the older Mask trait keep working as they are<br>
+//!Lines longer than the buffer's view width are scrolled horizontally within the terminal row (see `fit`), since backspaces cannot go back across a line wrap<br>
+
+use crate::{Edit, EditBuffer, Mask, MaskError, Motion};
+use std::sync::Arc;
+use unicode_width::UnicodeWidthStr;
+use zeroize::Zeroizing;
+
+///What a mask shows on screen for one password character (or any other piece of the line, like a strength meter)<br>
+///`text` is written as is (it may carry color codes), `width` is the number of cells it takes up<br>
+///The text is wiped from memory once the last clone of the glyph is dropped, since it may be a password character in cleartext<br>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glyph {
+    text: Arc<Zeroizing<String>>,
+    width: usize,
+}
+
+impl Glyph {
+    ///Glyph for text that is not part of the password, like a mask character or a label<br>
+    pub fn new(text: impl std::fmt::Display, width: usize) -> Self {
+        Self::cleartext(text.to_string(), width)
+    }
+
+    ///Glyph for a password character in cleartext (colored or not)<br>
+    ///The string is taken over as is, so no copy of it is left behind<br>
+    pub fn cleartext(text: String, width: usize) -> Self {
+        Self {
+            text: Arc::new(Zeroizing::new(text)),
+            width,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        self.text.as_str()
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+}
+
+///Contents of the input line after the prompt<br>
+///`cursor` is the number of glyphs before the terminal cursor<br>
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Line {
+    pub glyphs: Vec<Glyph>,
+    pub cursor: usize,
+}
+
+impl Line {
+    pub fn new(glyphs: Vec<Glyph>, cursor: usize) -> Self {
+        Self { glyphs, cursor }
+    }
+
+    ///Number of cells taken by the whole line<br>
+    pub fn width(&self) -> usize {
+        width(&self.glyphs)
+    }
+}
+
+///Number of terminal cells taken by plain text (no color codes), wide CJK characters and emoji count as 2<br>
+pub fn display_width(text: &str) -> usize {
+    text.width()
+}
+
+fn width(glyphs: &[Glyph]) -> usize {
+    glyphs.iter().map(|g| g.width).sum()
+}
+
+///Trait for masks that render the whole input line from the password state<br>
+///Implementing it is enough to be used as a Mask, see the blanket implementation<br>
+pub trait RenderMask {
+    ///A default implementation for the mask, which Mask::default hands back<br>
+    ///Named apart from Mask::default so `X::default()` is not ambiguous when both traits are in scope<br>
+    fn default_mask() -> Self
+    where
+        Self: Sized;
+
+    ///The input line for the password, `finished` is set once the user has provided EOF<br>
+    ///The line is drawn from the first edit on, so an empty password may render to something (ex: a hint)<br>
+    fn render(&self, password: &EditBuffer, finished: bool) -> Line;
+
+    #[cfg(feature = "colored")]
+    ///Set a color for the mask<br>
+    fn set_color(&mut self, _c: &str);
+
+    #[cfg(feature = "colored")]
+    ///Take the styles of a theme that concern the mask, the default implementation only takes the mask color<br>
+    fn set_theme(&mut self, theme: &crate::colors::Theme) {
+        self.set_color(&theme.mask);
+    }
+}
+
+///The shared renderer: brings the terminal from the `before` line to the `after` line<br>
+///Only the glyphs from the first difference onwards are rewritten, leftover cells are blanked out, and the cursor ends up at `after.cursor`<br>
+pub fn draw(o: &mut dyn std::io::Write, before: &Line, after: &Line) -> std::io::Result<()> {
+    let (from, to) = (before.cursor, after.cursor);
+    let (before, after) = (&before.glyphs[..], &after.glyphs[..]);
+    let backs = |n: usize| "\x08".repeat(n);
+
+    let first = before
+        .iter()
+        .zip(after.iter())
+        .take_while(|(b, a)| b == a)
+        .count();
+
+    if first == before.len() && first == after.len() {
+        //Nothing changed on screen, only the cursor moves
+        if to < from {
+            write!(o, "{}", backs(width(&after[to..from])))?;
+        } else {
+            for glyph in &after[from..to] {
+                o.write_all(glyph.text().as_bytes())?;
+            }
+        }
+        return Ok(());
+    }
+
+    if first < from {
+        write!(o, "{}", backs(width(&before[first..from])))?;
+    } else {
+        for glyph in &before[from..first] {
+            o.write_all(glyph.text().as_bytes())?;
+        }
+    }
+
+    for glyph in &after[first..] {
+        o.write_all(glyph.text().as_bytes())?;
+    }
+
+    let pad = width(&before[first..]).saturating_sub(width(&after[first..]));
+    write!(o, "{}{}", " ".repeat(pad), backs(pad + width(&after[to..])))?;
+
+    Ok(())
+}
+
+///Cuts a line down to the part that fits in `width` cells, keeping the cursor in view<br>
+///The window keeps the cursor as far right as it can, `<` and `>` stand in for the parts scrolled out on either side<br>
+///The result only depends on the line and the width, so the renderer can work out what is on screen by fitting the previous line again<br>
+pub fn fit(line: Line, width: usize) -> Line {
+    if line.width() <= width {
+        return line;
+    }
+
+    //Room left for the password once both markers are in
+    let inner = width.saturating_sub(2).max(1);
+    let glyphs = &line.glyphs;
+
+    let mut start = 0;
+    while start < line.cursor && self::width(&glyphs[start..line.cursor]) > inner {
+        start += 1;
+    }
+
+    let mut end = start;
+    let mut used = 0;
+    while end < glyphs.len() && used + glyphs[end].width <= inner {
+        used += glyphs[end].width;
+        end += 1;
+    }
+
+    let mut visible = Vec::with_capacity(end - start + 2);
+    if start > 0 {
+        visible.push(Glyph::new("<", 1));
+    }
+    visible.extend_from_slice(&glyphs[start..end]);
+    if end < glyphs.len() {
+        visible.push(Glyph::new(">", 1));
+    }
+
+    let cursor = (start > 0) as usize + line.cursor - start;
+    Line::new(visible, cursor)
+}
+
+///The line for the password as it goes on screen, fitted to the view width of the buffer<br>
+fn visible<M: RenderMask + ?Sized>(mask: &M, password: &EditBuffer, finished: bool) -> Line {
+    let line = match password.placeholder() {
+        //The hint stays after the cursor until something is typed
+        Some(hint) if password.is_empty() && !finished => Line::new(vec![hint.clone()], 0),
+        _ => mask.render(password, finished),
+    };
+    match password.view_width() {
+        Some(width) => fit(line, width),
+        None => line,
+    }
+}
+
+///What is currently on screen for the password<br>
+fn on_screen<M: RenderMask + ?Sized>(mask: &M, password: &EditBuffer) -> Line {
+    //Nothing has been drawn yet for a fresh buffer, other than its placeholder
+    match password.last_edit() {
+        Edit::None if password.placeholder().is_none() => Line::default(),
+        _ => visible(mask, password, false),
+    }
+}
+
+///Applies `op` to the password and redraws whatever changed<br>
+fn edit<M: RenderMask + ?Sized>(
+    mask: &M,
+    password: &mut EditBuffer,
+    o: &mut dyn std::io::Write,
+    op: impl FnOnce(&mut EditBuffer),
+) -> Result<(), MaskError> {
+    let before = on_screen(mask, password);
+    op(password);
+    draw(o, &before, &visible(mask, password, false))?;
+    Ok(())
+}
+
+///Adapter running a RenderMask through the shared renderer<br>
+impl<M: RenderMask> Mask for M {
+    fn default() -> Self {
+        M::default_mask()
+    }
+
+    fn feed_password(
+        &self,
+        password: &mut EditBuffer,
+        ch: char,
+        o: &mut dyn std::io::Write,
+    ) -> Result<(), MaskError> {
+        edit(self, password, o, |p| p.insert(ch))
+    }
+
+    fn paste_password(
+        &self,
+        password: &mut EditBuffer,
+        text: &str,
+        o: &mut dyn std::io::Write,
+    ) -> Result<(), MaskError> {
+        edit(self, password, o, |p| p.paste(text))
+    }
+
+    fn redraw_password(
+        &self,
+        password: &EditBuffer,
+        o: &mut dyn std::io::Write,
+    ) -> Result<(), MaskError> {
+        draw(o, &Line::default(), &visible(self, password, false))?;
+        Ok(())
+    }
+
+    fn pop_password(
+        &self,
+        password: &mut EditBuffer,
+        o: &mut dyn std::io::Write,
+    ) -> Result<(), MaskError> {
+        edit(self, password, o, |p| {
+            p.backspace();
+        })
+    }
+
+    fn delete_password(
+        &self,
+        password: &mut EditBuffer,
+        o: &mut dyn std::io::Write,
+    ) -> Result<(), MaskError> {
+        edit(self, password, o, |p| {
+            p.delete();
+        })
+    }
+
+    fn move_cursor(
+        &self,
+        password: &mut EditBuffer,
+        motion: Motion,
+        o: &mut dyn std::io::Write,
+    ) -> Result<(), MaskError> {
+        edit(self, password, o, |p| {
+            p.move_cursor(motion);
+        })
+    }
+
+    fn end_password(
+        &self,
+        password: &EditBuffer,
+        o: &mut dyn std::io::Write,
+    ) -> Result<(), MaskError> {
+        let before = on_screen(self, password);
+        draw(o, &before, &visible(self, password, true))?;
+        writeln!(o)?;
+        Ok(())
+    }
+
+    #[cfg(feature = "colored")]
+    fn set_color(&mut self, c: &str) {
+        <M as RenderMask>::set_color(self, c)
+    }
+
+    #[cfg(feature = "colored")]
+    fn set_theme(&mut self, theme: &crate::colors::Theme) {
+        <M as RenderMask>::set_theme(self, theme)
+    }
+}
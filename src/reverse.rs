@@ -4,105 +4,164 @@
 //!The masks are only available if the `reverse` feature flag is enabled<br>
 
 use crate::masks::DEFAULT_MASK_CHAR;
-use crate::Mask;
+use crate::render::{display_width, Glyph, Line, RenderMask};
+use crate::spec::{self, FromSpec, MaskSpec, SpecError};
+use crate::{Edit, EditBuffer};
+use std::fmt::{self, Display};
+use std::str::FromStr;
 use std::sync::Arc;
 
 #[cfg(feature = "colored")]
 use crate::colors;
 
-#[cfg(feature = "colored")]
-use colored::{Color, Colorize};
-
 ///Mask that shows last character of password in plaintext and rest all characters masked<br>
 ///Similar to JS prompts on websites<br>
+///When editing in the middle of the password, the character just typed (the one before the cursor) is the one shown<br>
 pub struct OneReverse {
     mask: Arc<str>,
-    spaces: Arc<str>,
-    backs: Arc<str>,
 
     #[cfg(feature = "colored")]
     color: Arc<str>,
+
+    #[cfg(feature = "colored")]
+    reveal: Arc<str>,
 }
 
 impl OneReverse {
     pub fn new(mask: &str) -> Self {
         Self {
             mask: Arc::from(mask),
-            spaces: Arc::from(" ".repeat(mask.len())),
-            backs: Arc::from("\x08".repeat(mask.len())),
-        
+
             #[cfg(feature = "colored")]
             color: Arc::from(colors::DEFAULT_MASK_COLOR),
+            #[cfg(feature = "colored")]
+            reveal: Arc::from(colors::DEFAULT_REVEAL_STYLE),
         }
     }
-}
 
+    ///Style for the character shown in cleartext, see colors::Style<br>
+    #[cfg(feature = "colored")]
+    pub fn set_reveal_style(&mut self, style: &str) {
+        self.reveal = Arc::from(style);
+    }
 
-impl Mask for OneReverse {
-    fn default() -> Self {
-        Self::new(DEFAULT_MASK_CHAR)
+    ///Glyphs for the password, with the character at `reveal` in cleartext<br>
+    fn glyphs(&self, password: &EditBuffer, reveal: Option<usize>) -> Vec<Glyph> {
+        #[cfg(feature = "colored")]
+        let masked = Glyph::new(
+            colors::paint(&self.mask, &self.color),
+            display_width(&self.mask),
+        );
+        #[cfg(not(feature = "colored"))]
+        let masked = Glyph::new(&self.mask, display_width(&self.mask));
+
+        password
+            .graphemes()
+            .enumerate()
+            .map(|(i, g)| match Some(i) == reveal {
+                true => {
+                    #[cfg(feature = "colored")]
+                    let text = colors::paint(g, &self.reveal);
+                    #[cfg(not(feature = "colored"))]
+                    let text = g.to_string();
+
+                    Glyph::cleartext(text, display_width(g))
+                }
+                false => masked.clone(),
+            })
+            .collect()
     }
 
-    fn feed_password(
-        &self,
-        password: &mut String,
-        ch: char,
-        o: &mut dyn std::io::Write,
-    ) -> Result<(), String> {
-        let buffer = match password.is_empty() {
-            true => ch.to_string(),
-            false => {
-                format!("\x08{mask}{ch}", mask = &self.mask)
-            }
-        };
+    ///Spec for a reverse mask named `name`, shared by both masks<br>
+    fn spec(&self, name: &str) -> MaskSpec {
+        let spec = MaskSpec::new(name).arg(&self.mask);
 
         #[cfg(feature = "colored")]
-        let buffer = buffer.color(self.color.as_ref());
-
-        write!(o , "{}", buffer).map_err(|e| e.to_string())?;
-        password.push(ch);
-        Ok(())
-    }
-
-    fn pop_password(
-        &self,
-        password: &mut String,
-        o: &mut dyn std::io::Write,
-    ) -> Result<(), String> {
-        match password.pop() {
-            Some(_) => {
-                let buffer = format!(
-                    "{backs}{spaces}{backs}",
-                    backs = &self.backs,
-                    spaces = &self.spaces
-                );
-
-                #[cfg(feature = "colored")]
-                let buffer = buffer.color(self.color.as_ref());
-
-                write!(o , "{}", buffer).map_err(|e| e.to_string())?;
-            }
-            None => {}
+        let spec = spec.color(&self.color);
+
+        #[cfg(feature = "colored")]
+        let spec = match self.reveal.as_ref() == colors::DEFAULT_REVEAL_STYLE {
+            true => spec,
+            false => spec.option("reveal", &self.reveal),
         };
 
-        Ok(())
+        spec
     }
 
-    fn end_password(&self, o: &mut dyn std::io::Write) -> Result<(), String> {
-        let buffer = format!("\x08{mask}\n", mask = &self.mask);
+    ///The character before the cursor is shown right after it has been typed<br>
+    fn revealed(password: &EditBuffer) -> Option<usize> {
+        match password.last_edit() {
+            Edit::Insert => password.cursor().checked_sub(1),
+            _ => None,
+        }
+    }
+}
 
-        #[cfg(feature = "colored")]
-        let buffer = buffer.color(self.color.as_ref());
+impl RenderMask for OneReverse {
+    fn default_mask() -> Self {
+        Self::new(DEFAULT_MASK_CHAR)
+    }
+
+    ///Once the password has been entered, every character is masked<br>
+    fn render(&self, password: &EditBuffer, finished: bool) -> Line {
+        let reveal = match finished {
+            true => None,
+            false => Self::revealed(password),
+        };
 
-        // o.write(buffer.as_bytes()).map_err(|e| e.to_string())?;
-        write!(o , "{}" , buffer).map_err(|e| e.to_string())?;
-        Ok(())
+        Line::new(self.glyphs(password, reveal), password.cursor())
     }
 
     #[cfg(feature = "colored")]
-    fn set_color(&mut self, c: &str) -> () {
+    fn set_color(&mut self, c: &str) {
         self.color = Arc::from(c);
     }
+
+    #[cfg(feature = "colored")]
+    fn set_theme(&mut self, theme: &colors::Theme) {
+        self.color = Arc::from(theme.mask.as_str());
+        self.reveal = Arc::from(theme.reveal.as_str());
+    }
+}
+
+///`onereverse` or `or`, with an optional mask, and `color` and `reveal` (style of the character shown) options<br>
+impl FromSpec for OneReverse {
+    const NAMES: &'static [&'static str] = &["onereverse", "or"];
+    const ARGS: &'static [&'static str] = &["mask"];
+    const OPTIONS: &'static [&'static str] = &["color", "reveal"];
+    const DESCRIPTION: &'static str = "Masked, with the character just typed shown in cleartext";
+
+    fn from_spec(spec: &MaskSpec) -> Result<Self, SpecError> {
+        let mut mask = match spec.positional(0) {
+            Some(arg) => Self::new(&arg.value),
+            None => Self::default_mask(),
+        };
+        spec::apply_color(spec, &mut mask)?;
+
+        //Like color, accepted and ignored without the colored feature
+        #[cfg(feature = "colored")]
+        if let Some(arg) = spec.get("reveal") {
+            arg.value
+                .parse::<colors::Style>()
+                .map_err(|e| SpecError::new(arg.position, e))?;
+            mask.set_reveal_style(&arg.value);
+        }
+        Ok(mask)
+    }
+}
+
+impl FromStr for OneReverse {
+    type Err = SpecError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        spec::parse(s)
+    }
+}
+
+impl Display for OneReverse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.spec(Self::NAMES[0]).fmt(f)
+    }
 }
 
 ///Composition due to lack of inheritance in Rust<br>
@@ -116,59 +175,69 @@ impl MimiReverse {
     pub fn new(mask: &str) -> Self {
         Self(OneReverse::new(mask))
     }
+
+    ///Style for the character shown in cleartext, see colors::Style<br>
+    #[cfg(feature = "colored")]
+    pub fn set_reveal_style(&mut self, style: &str) {
+        self.0.set_reveal_style(style);
+    }
+
+    ///Same as OneReverse, but the character before the cursor stays visible after a backspace as well<br>
+    fn revealed(password: &EditBuffer) -> Option<usize> {
+        match password.last_edit() {
+            Edit::Insert | Edit::Backspace => password.cursor().checked_sub(1),
+            _ => None,
+        }
+    }
 }
 
-impl Mask for MimiReverse {
-    fn default() -> Self {
+impl RenderMask for MimiReverse {
+    fn default_mask() -> Self {
         Self::new(DEFAULT_MASK_CHAR)
     }
 
-    fn feed_password(
-        &self,
-        password: &mut String,
-        ch: char,
-        o: &mut dyn std::io::Write,
-    ) -> Result<(), String> {
-        self.0.feed_password(password, ch, o)
-    }
-
-    fn pop_password(
-        &self,
-        password: &mut String,
-        o: &mut dyn std::io::Write,
-    ) -> Result<(), String> {
-
-        let buffer = match password.len() {
-            0 => {
-                return Ok(());
-            }
-            1 => "\x08 \x08".to_string(),
-            _ => {
-                format!(
-                    "\x08{backs} {spaces}{backs}\x08{last_char}",
-                    backs = &self.0.backs,
-                    spaces = &self.0.spaces,
-                    last_char = &password.chars().rev().take(2).last().unwrap()
-                )
-            }
+    fn render(&self, password: &EditBuffer, finished: bool) -> Line {
+        let reveal = match finished {
+            true => None,
+            false => Self::revealed(password),
         };
 
-        #[cfg(feature = "colored")]
-        let buffer = buffer.color(self.0.color.as_ref());
+        Line::new(self.0.glyphs(password, reveal), password.cursor())
+    }
+
+    #[cfg(feature = "colored")]
+    fn set_color(&mut self, c: &str) {
+        self.0.color = Arc::from(c);
+    }
+
+    #[cfg(feature = "colored")]
+    fn set_theme(&mut self, theme: &colors::Theme) {
+        <OneReverse as RenderMask>::set_theme(&mut self.0, theme)
+    }
+}
 
-        let _ = password.pop();
+///`mimireverse`, `mimi` or `mr`, with the same arguments as onereverse<br>
+impl FromSpec for MimiReverse {
+    const NAMES: &'static [&'static str] = &["mimireverse", "mimi", "mr"];
+    const ARGS: &'static [&'static str] = &["mask"];
+    const OPTIONS: &'static [&'static str] = &["color", "reveal"];
+    const DESCRIPTION: &'static str = "Like onereverse, and the character before the cursor stays shown after a backspace";
 
-        // o.write(buffer.as_bytes()).map_err(|e| e.to_string())?;
-        write!(o , "{}", buffer).map_err(|e| e.to_string())?;
-        Ok(())
+    fn from_spec(spec: &MaskSpec) -> Result<Self, SpecError> {
+        <OneReverse as FromSpec>::from_spec(spec).map(Self)
     }
+}
 
-    fn end_password(&self, o: &mut dyn std::io::Write) -> Result<(), String> {
-        self.0.end_password(o)
+impl FromStr for MimiReverse {
+    type Err = SpecError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        spec::parse(s)
     }
+}
 
-    #[cfg(feature = "colored")]
-    fn set_color(&mut self, c: &str) -> () {
-        self.0.set_color(c);
+impl Display for MimiReverse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.spec(Self::NAMES[0]).fmt(f)
     }
 }
@@ -0,0 +1,387 @@
+//!Scripted input: a fixed run of tokens and errors replayed as an InputStream, for tests, demos and bug reports<br>
+//!Scripts are built in code or written in a small language: characters stand for themselves and keys go in angle brackets, ex: `hel<BS>lo<Left><C-c>`<br>
+//!Keys: `<BS>`, `<Del>`, `<Left>`, `<Right>`, `<Home>`, `<End>`, `<C-Left>`, `<C-Right>`, `<F1>`..`<F12>`, `<A-x>` (Alt), `<Paste:text>`, `<Redraw>`, `<Ignore>` and `<Enter>` (or `<EOF>`, `<C-d>`) to end the input<br>
+//!Errors: `<C-c>` (LibError::UserInterrupt), `<Timeout>` and `<Error:message>` (LibError::Other)<br>
+//!`<Wait:50ms>` (or `<Wait:1.5s>`) delays the step after it. `\` takes the next character literally, ex: `\<` or `\\`<br>
+//!A Recorder wraps another input stream (ex: Getch) and records what it reads, so a live session can be replayed later. Recordings hold the password in cleartext<br>
+
+use crate::{InputStream, InputToken, LibError};
+use std::collections::VecDeque;
+use std::fmt::{self, Display, Write};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+///One step of a script: a token, or an error the input stream returns<br>
+#[derive(Debug, Clone)]
+pub enum Step {
+    Token(InputToken),
+    ///LibError::UserInterrupt (Ctrl-C)<br>
+    Interrupt,
+    ///LibError::Timeout<br>
+    Timeout,
+    ///LibError::Other with the message<br>
+    Error(String),
+}
+
+impl Step {
+    ///What the input stream returns for this step<br>
+    pub fn into_result(self) -> Result<InputToken, LibError> {
+        match self {
+            Step::Token(token) => Ok(token),
+            Step::Interrupt => Err(LibError::UserInterrupt),
+            Step::Timeout => Err(LibError::Timeout),
+            Step::Error(message) => Err(LibError::Other(message)),
+        }
+    }
+
+    ///The step replaying what an input stream returned, errors other than interrupts and timeouts keep their message<br>
+    pub fn from_result(result: &Result<InputToken, LibError>) -> Self {
+        match result {
+            Ok(token) => Step::Token(token.clone()),
+            Err(LibError::UserInterrupt) => Step::Interrupt,
+            Err(LibError::Timeout) => Step::Timeout,
+            Err(e) => Step::Error(e.to_string()),
+        }
+    }
+}
+
+impl From<InputToken> for Step {
+    fn from(token: InputToken) -> Self {
+        Step::Token(token)
+    }
+}
+
+///Error in a script, with the character position it was found at<br>
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{message} at position {position}")]
+pub struct ScriptError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl ScriptError {
+    pub fn new(position: usize, message: impl Into<String>) -> Self {
+        Self {
+            position,
+            message: message.into(),
+        }
+    }
+}
+
+///Input stream replaying a fixed run of steps, each after an optional delay. Once the steps run out, every read is EOF<br>
+///Ex: `Script::new().text("hel").token(InputToken::Backspace).step(Step::Interrupt)`, or `"hel<BS><C-c>".parse::<Script>()?`<br>
+#[derive(Debug, Clone, Default)]
+pub struct Script {
+    steps: VecDeque<(Duration, Step)>,
+    //Delay for the next step added
+    delay: Duration,
+}
+
+impl Script {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    ///Add a step, after the delay set with delay() if any<br>
+    pub fn step(mut self, step: impl Into<Step>) -> Self {
+        let delay = std::mem::take(&mut self.delay);
+        self.push(delay, step.into());
+        self
+    }
+
+    pub fn token(self, token: InputToken) -> Self {
+        self.step(token)
+    }
+
+    ///A Character token for every character of `text`<br>
+    pub fn text(self, text: &str) -> Self {
+        text.chars()
+            .fold(self, |script, ch| script.token(InputToken::Character(ch)))
+    }
+
+    ///Wait `delay` before the next step<br>
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay += delay;
+        self
+    }
+
+    fn push(&mut self, delay: Duration, step: Step) {
+        self.steps.push_back((delay, step));
+    }
+
+    ///Steps left, with the delay before each one<br>
+    pub fn steps(&self) -> impl Iterator<Item = &(Duration, Step)> {
+        self.steps.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+impl FromIterator<InputToken> for Script {
+    fn from_iter<T: IntoIterator<Item = InputToken>>(tokens: T) -> Self {
+        tokens.into_iter().fold(Script::new(), Script::token)
+    }
+}
+
+impl InputStream for Script {
+    fn get_token(&mut self) -> Result<InputToken, LibError> {
+        match self.steps.pop_front() {
+            Some((delay, step)) => {
+                std::thread::sleep(delay);
+                step.into_result()
+            }
+            None => Ok(InputToken::EOF),
+        }
+    }
+
+    ///A delay longer than `timeout` times out, and the rest of the delay is waited out by the next read<br>
+    fn get_token_timeout(&mut self, timeout: Duration) -> Result<InputToken, LibError> {
+        match self.steps.front_mut() {
+            Some((delay, _)) if *delay > timeout => {
+                std::thread::sleep(timeout);
+                *delay -= timeout;
+                Err(LibError::Timeout)
+            }
+            _ => self.get_token(),
+        }
+    }
+}
+
+///Writes the script back in the script language, so a recording can be saved and parsed again<br>
+impl Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (delay, step) in &self.steps {
+            if !delay.is_zero() {
+                write!(f, "<Wait:{}ms>", delay.as_millis())?;
+            }
+
+            match step {
+                Step::Token(InputToken::Character(ch)) => {
+                    if matches!(ch, '<' | '\\') {
+                        f.write_char('\\')?;
+                    }
+                    f.write_char(*ch)?;
+                }
+                Step::Token(token) => write!(f, "<{}>", key_name(token))?,
+                Step::Interrupt => f.write_str("<C-c>")?,
+                Step::Timeout => f.write_str("<Timeout>")?,
+                Step::Error(message) => write!(f, "<Error:{}>", escape(message))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+///The name of a key in the script language, without the brackets<br>
+fn key_name(token: &InputToken) -> String {
+    match token {
+        InputToken::Character(ch) => escape(&ch.to_string()),
+        InputToken::Backspace => "BS".into(),
+        InputToken::Delete => "Del".into(),
+        InputToken::Left => "Left".into(),
+        InputToken::Right => "Right".into(),
+        InputToken::Home => "Home".into(),
+        InputToken::End => "End".into(),
+        InputToken::WordLeft => "C-Left".into(),
+        InputToken::WordRight => "C-Right".into(),
+        InputToken::Function(n) => format!("F{}", n),
+        InputToken::Alt(ch) => format!("A-{}", escape(&ch.to_string())),
+        InputToken::Paste(text) => format!("Paste:{}", escape(text)),
+        InputToken::Redraw => "Redraw".into(),
+        InputToken::EOF => "Enter".into(),
+        InputToken::IgnoreToken => "Ignore".into(),
+    }
+}
+
+///Escapes the characters that end a key (`>`) or escape the next one (`\`)<br>
+fn escape(text: &str) -> String {
+    text.chars().fold(String::new(), |mut escaped, ch| {
+        if matches!(ch, '>' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+        escaped
+    })
+}
+
+impl FromStr for Script {
+    type Err = ScriptError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars().enumerate().peekable();
+        let mut script = Script::new();
+
+        while let Some((position, ch)) = chars.next() {
+            match ch {
+                '\\' => match chars.next() {
+                    Some((_, ch)) => script = script.token(InputToken::Character(ch)),
+                    None => return Err(ScriptError::new(position, "nothing to escape")),
+                },
+                '<' => {
+                    //The key runs up to the next unescaped `>`
+                    let mut key = String::new();
+                    loop {
+                        match chars.next() {
+                            Some((_, '>')) => break,
+                            Some((_, '\\')) => match chars.next() {
+                                Some((_, ch)) => key.push(ch),
+                                None => return Err(ScriptError::new(position, "unclosed key")),
+                            },
+                            Some((_, ch)) => key.push(ch),
+                            None => return Err(ScriptError::new(position, "unclosed key")),
+                        }
+                    }
+
+                    script = match key.split_once(':') {
+                        Some((name, value)) if name.eq_ignore_ascii_case("wait") => {
+                            script.delay(duration(value).ok_or_else(|| {
+                                ScriptError::new(position, format!("invalid delay {:?}", value))
+                            })?)
+                        }
+                        Some((name, value)) if name.eq_ignore_ascii_case("paste") => {
+                            script.token(InputToken::Paste(value.to_string()))
+                        }
+                        Some((name, value)) if name.eq_ignore_ascii_case("error") => {
+                            script.step(Step::Error(value.to_string()))
+                        }
+                        _ => script.step(key_step(&key).ok_or_else(|| {
+                            ScriptError::new(position, format!("unknown key <{}>", key))
+                        })?),
+                    };
+                }
+                ch => script = script.token(InputToken::Character(ch)),
+            }
+        }
+
+        Ok(script)
+    }
+}
+
+///The step for a key without a value, ex: `BS`, `F5`, `A-x`<br>
+fn key_step(key: &str) -> Option<Step> {
+    //Alt keeps the case of its character
+    if let Some(rest) = key.strip_prefix("A-").or_else(|| key.strip_prefix("a-")) {
+        let mut chars = rest.chars();
+        return match (chars.next(), chars.next()) {
+            (Some(ch), None) => Some(InputToken::Alt(ch).into()),
+            _ => None,
+        };
+    }
+
+    let lower = key.to_ascii_lowercase();
+    if let Some(n) = lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+        return (1..=12)
+            .contains(&n)
+            .then_some(InputToken::Function(n).into());
+    }
+
+    let token = match lower.as_str() {
+        "bs" | "backspace" => InputToken::Backspace,
+        "del" | "delete" => InputToken::Delete,
+        "left" => InputToken::Left,
+        "right" => InputToken::Right,
+        "home" => InputToken::Home,
+        "end" => InputToken::End,
+        "c-left" => InputToken::WordLeft,
+        "c-right" => InputToken::WordRight,
+        "redraw" => InputToken::Redraw,
+        "ignore" => InputToken::IgnoreToken,
+        "enter" | "cr" | "eof" | "c-d" => InputToken::EOF,
+        "c-c" => return Some(Step::Interrupt),
+        "timeout" => return Some(Step::Timeout),
+        _ => return None,
+    };
+    Some(token.into())
+}
+
+///`50ms`, `1.5s`<br>
+fn duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let (number, unit) = match value.strip_suffix("ms") {
+        Some(number) => (number, 1000.0),
+        None => (value.strip_suffix('s')?, 1.0),
+    };
+    Duration::try_from_secs_f64(number.trim().parse::<f64>().ok()? / unit).ok()
+}
+
+///Shared handle to what a Recorder has recorded, usable after the recorder has been handed to GPass<br>
+#[derive(Debug, Clone, Default)]
+pub struct Recording(Arc<Mutex<Script>>);
+
+impl Recording {
+    ///The steps recorded so far, as a script that replays them<br>
+    pub fn script(&self) -> Script {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+///Input stream recording every token (and error) read from another one, along with the time between them<br>
+///Ex: `let recorder = Recorder::new(Getch::new()); let recording = recorder.recording();`, then after the prompt `recording.script().to_string()`<br>
+pub struct Recorder<I: InputStream> {
+    inner: I,
+    recording: Recording,
+    timed: bool,
+    last: Option<Instant>,
+}
+
+impl<I: InputStream> Recorder<I> {
+    pub fn new(inner: I) -> Self {
+        Self {
+            inner,
+            recording: Recording::default(),
+            timed: true,
+            last: None,
+        }
+    }
+
+    ///Whether the time between tokens is recorded as delays (the default), the wait for the first token is not<br>
+    pub fn set_timed(&mut self, timed: bool) {
+        self.timed = timed;
+    }
+
+    pub fn recording(&self) -> Recording {
+        self.recording.clone()
+    }
+
+    fn record(&mut self, result: Result<InputToken, LibError>) -> Result<InputToken, LibError> {
+        let now = Instant::now();
+        let delay = match (self.timed, self.last) {
+            (true, Some(last)) => now - last,
+            _ => Duration::ZERO,
+        };
+        self.last = Some(now);
+
+        self.recording
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(delay, Step::from_result(&result));
+        result
+    }
+}
+
+impl<I: InputStream> InputStream for Recorder<I> {
+    fn get_token(&mut self) -> Result<InputToken, LibError> {
+        let result = self.inner.get_token();
+        self.record(result)
+    }
+
+    fn get_token_timeout(&mut self, timeout: Duration) -> Result<InputToken, LibError> {
+        let result = self.inner.get_token_timeout(timeout);
+        self.record(result)
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.inner.is_terminal()
+    }
+}
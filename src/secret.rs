@@ -0,0 +1,149 @@
+//!Zeroizing container for passwords<br>
+//!The password is kept in a single preallocated buffer that is edited in place. When it has to grow, the contents are copied into a bigger buffer and the old one is wiped before it is freed<br>
+//!The buffer is wiped on drop, and Debug/Display never show the contents. Use `expose()` to read the password<br>
+
+use zeroize::Zeroize;
+
+///Bytes preallocated for a new secret, enough for any sensible passphrase without ever growing<br>
+pub const DEFAULT_SECRET_CAPACITY: usize = 256;
+
+///A password that is wiped from memory when dropped<br>
+pub struct Secret {
+    bytes: Vec<u8>,
+}
+
+impl Secret {
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_SECRET_CAPACITY)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            bytes: Vec::with_capacity(capacity),
+        }
+    }
+
+    ///Access the password in cleartext<br>
+    pub fn expose(&self) -> &str {
+        std::str::from_utf8(&self.bytes).expect("Secret only ever holds whole UTF-8 characters")
+    }
+
+    ///Length in bytes<br>
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.bytes.capacity()
+    }
+
+    ///Compare two secrets in constant time<br>
+    ///The time taken depends only on the lengths of the secrets, never on where they differ<br>
+    pub fn ct_eq(&self, other: &Secret) -> bool {
+        let (a, b) = (&self.bytes, &other.bytes);
+        let mut diff = u8::from(a.len() != b.len());
+
+        for i in 0..a.len().max(b.len()) {
+            diff |= a.get(i).unwrap_or(&0) ^ b.get(i).unwrap_or(&0);
+        }
+
+        std::hint::black_box(diff) == 0
+    }
+
+    ///Insert a character at a byte offset<br>
+    pub fn insert(&mut self, at: usize, ch: char) {
+        let mut encoded = [0u8; 4];
+        let encoded = ch.encode_utf8(&mut encoded).as_bytes();
+
+        self.reserve(encoded.len());
+
+        //Capacity is already there, so none of these inserts reallocate
+        for (i, byte) in encoded.iter().enumerate() {
+            self.bytes.insert(at + i, *byte);
+        }
+    }
+
+    ///Append a character<br>
+    pub fn push(&mut self, ch: char) {
+        self.insert(self.bytes.len(), ch);
+    }
+
+    ///Remove the character at a byte offset<br>
+    pub fn remove(&mut self, at: usize) -> char {
+        let ch = self.expose()[at..]
+            .chars()
+            .next()
+            .expect("Cannot remove a character past the end of the secret");
+
+        self.remove_range(at..at + ch.len_utf8());
+        ch
+    }
+
+    ///Remove a byte range, which has to start and end on character boundaries<br>
+    pub fn remove_range(&mut self, range: std::ops::Range<usize>) {
+        let password = self.expose();
+        assert!(
+            password.is_char_boundary(range.start) && password.is_char_boundary(range.end),
+            "Secret ranges must lie on character boundaries"
+        );
+
+        let width = range.len();
+        let len = self.bytes.len();
+
+        //Shift the tail over the range, then wipe the bytes left over at the end before dropping them
+        self.bytes.copy_within(range.end.., range.start);
+        self.bytes[len - width..].zeroize();
+        self.bytes.truncate(len - width);
+    }
+
+    ///Make room for `additional` more bytes, without leaving a copy of the password in freed memory<br>
+    fn reserve(&mut self, additional: usize) {
+        if self.bytes.len() + additional <= self.bytes.capacity() {
+            return;
+        }
+
+        let capacity = (self.bytes.capacity() * 2).max(self.bytes.len() + additional);
+        let mut bytes = Vec::with_capacity(capacity);
+        bytes.extend_from_slice(&self.bytes);
+
+        self.bytes.zeroize();
+        self.bytes = bytes;
+    }
+}
+
+impl Default for Secret {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+///Takes over the buffer of the string, no copy is made<br>
+impl From<String> for Secret {
+    fn from(s: String) -> Self {
+        Self {
+            bytes: s.into_bytes(),
+        }
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.bytes.zeroize();
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Secret([REDACTED])")
+    }
+}
+
+impl std::fmt::Display for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[REDACTED]")
+    }
+}
@@ -0,0 +1,102 @@
+//!A prompt as a state machine, fed one token at a time<br>
+//!get_password and get_password_async only differ in how they wait for the next token, the entries, policy retries and confirmation are all handled here<br>
+
+use crate::policy::Policy;
+use crate::{Confirm, EditBuffer, GPass, InputToken, LibError, Secret};
+use std::time::{Duration, Instant};
+
+///A prompt in progress: the entry being typed, and the retries and confirmation around it<br>
+pub(crate) struct Session {
+    confirm: Option<Confirm>,
+    policy: Option<Policy>,
+    started: Instant,
+    violations: usize,
+    mismatches: usize,
+
+    ///The entry being typed, the prompt it was asked with and when the last token for it came in<br>
+    prompt: String,
+    password: EditBuffer,
+    last_input: Instant,
+
+    ///The first entry, once it passed the policy and its confirmation is being typed<br>
+    first: Option<Secret>,
+}
+
+impl GPass {
+    ///Starts the prompt, taking over the confirmation and policy settings, and prints the first prompt<br>
+    pub(crate) fn start_session(&mut self) -> Result<Session, LibError> {
+        self.settle_colors();
+        let prompt = self.prompt.clone();
+
+        let mut session = Session {
+            confirm: self.confirm.take(),
+            policy: self.policy.take(),
+            started: Instant::now(),
+            violations: 0,
+            mismatches: 0,
+            prompt: String::new(),
+            password: EditBuffer::new(),
+            last_input: Instant::now(),
+            first: None,
+        };
+        self.start_entry(&mut session, prompt)?;
+        Ok(session)
+    }
+
+    ///How long to wait for the next token, None if there is no limit<br>
+    pub(crate) fn token_timeout(&self, session: &Session) -> Option<Duration> {
+        self.timeout
+            .and_then(|t| t.remaining(session.started, session.last_input))
+    }
+
+    ///Applies the next token (or the error getting it) to the prompt<br>
+    ///Returns the password once it has been entered, passed the policy and been confirmed<br>
+    pub(crate) fn step(
+        &mut self,
+        session: &mut Session,
+        token: Result<InputToken, LibError>,
+    ) -> Result<Option<Secret>, LibError> {
+        session.last_input = Instant::now();
+
+        if !self.apply_token(&session.prompt, &mut session.password, token)? {
+            self.output_stream.flush().map_err(LibError::IOError)?;
+            return Ok(None);
+        }
+
+        let entry = std::mem::take(&mut session.password).into_secret();
+        let policy = session.policy.as_ref();
+
+        let next = match (session.first.take(), session.confirm.as_ref()) {
+            //The first entry is asked again if it does not pass the policy
+            (None, _) if self.violates(policy, &entry, &mut session.violations)? => {
+                self.prompt.clone()
+            }
+            (None, Some(confirm)) => {
+                session.first = Some(entry);
+                confirm.prompt.clone()
+            }
+            (None, None) => return Ok(Some(entry)),
+            //Both entries are asked again if they do not match
+            (Some(first), Some(confirm))
+                if self.mismatches(confirm, &first, &entry, &mut session.mismatches)? =>
+            {
+                self.prompt.clone()
+            }
+            (Some(first), _) => return Ok(Some(first)),
+        };
+
+        self.start_entry(session, next)?;
+        Ok(None)
+    }
+
+    ///Prints `prompt` and starts a new entry after it<br>
+    fn start_entry(&mut self, session: &mut Session, prompt: String) -> Result<(), LibError> {
+        session.password = self.edit_buffer(&prompt);
+        self.start_line(&prompt, &session.password)?;
+        self.output_stream.flush().map_err(LibError::IOError)?;
+
+        session.prompt = prompt;
+        session.last_input = Instant::now();
+        Ok(())
+    }
+}
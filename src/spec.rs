@@ -0,0 +1,495 @@
+//!Mask specifications, the text form of a mask used by environment variables and command line flags<br>
+//!A spec is a mask name, optionally followed by arguments in `()`, `[]` or `{}`: `standard`, `standard(#)`, `or("#", color=red)`<br>
+//!Arguments are either positional or `key=value` options, values are bare text or quoted with `"` or `'`<br>
+//!Inside quotes, `\"`, `\'`, `\\`, `\n`, `\r`, `\t`, `\0` and `\u{XXXX}` are escapes, outside of them `\` takes the next character as is (ex: `standard(\,)`)<br>
+//!Names are case insensitive. Every built-in mask implements FromStr and Display with this grammar, and displays to a spec that parses back to the same mask<br>
+
+use std::fmt::{self, Display, Write};
+use std::str::FromStr;
+
+///Error in a mask spec, `position` is the offset of the offending character (counted in characters, from 0)<br>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpecError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl SpecError {
+    pub fn new(position: usize, message: impl Into<String>) -> Self {
+        Self {
+            position,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for SpecError {}
+
+///One argument of a spec, `key` is None for positional arguments<br>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Arg {
+    pub key: Option<String>,
+    pub value: String,
+    ///Where the argument starts in the spec<br>
+    pub position: usize,
+}
+
+///A parsed mask spec<br>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaskSpec {
+    ///The mask name as written<br>
+    pub name: String,
+    pub args: Vec<Arg>,
+    ///Where the name starts in the spec<br>
+    pub position: usize,
+}
+
+impl MaskSpec {
+    ///Spec without arguments, to be built up with `arg` and `option`<br>
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            args: Vec::new(),
+            position: 0,
+        }
+    }
+
+    ///Adds a positional argument<br>
+    pub fn arg(mut self, value: &str) -> Self {
+        self.args.push(Arg {
+            key: None,
+            value: value.to_string(),
+            position: 0,
+        });
+        self
+    }
+
+    ///Adds a `key=value` option<br>
+    pub fn option(mut self, key: &str, value: impl Display) -> Self {
+        self.args.push(Arg {
+            key: Some(key.to_string()),
+            value: value.to_string(),
+            position: 0,
+        });
+        self
+    }
+
+    ///Adds the color option, unless it is the default mask color<br>
+    #[cfg(feature = "colored")]
+    pub(crate) fn color(self, color: &str) -> Self {
+        match color == crate::colors::DEFAULT_MASK_COLOR {
+            true => self,
+            false => self.option("color", color),
+        }
+    }
+
+    ///Whether the spec names one of `names` (case insensitive)<br>
+    pub fn is(&self, names: &[&str]) -> bool {
+        names
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(&self.name))
+    }
+
+    ///Positional argument number `index`<br>
+    pub fn positional(&self, index: usize) -> Option<&Arg> {
+        self.args.iter().filter(|arg| arg.key.is_none()).nth(index)
+    }
+
+    ///Value of the option `key`<br>
+    pub fn get(&self, key: &str) -> Option<&Arg> {
+        self.args.iter().find(|arg| {
+            arg.key
+                .as_deref()
+                .is_some_and(|k| k.eq_ignore_ascii_case(key))
+        })
+    }
+
+    ///Checks the arguments against what the mask takes: at most `positional` positional arguments, given before the options, and only the `options` listed, each at most once<br>
+    pub fn expect(&self, positional: usize, options: &[&str]) -> Result<(), SpecError> {
+        let mut seen: Vec<&str> = Vec::new();
+        let mut count = 0;
+
+        for arg in &self.args {
+            match arg.key {
+                None if !seen.is_empty() => {
+                    return Err(SpecError::new(
+                        arg.position,
+                        "positional argument after an option",
+                    ))
+                }
+                None => {
+                    count += 1;
+                    if count > positional {
+                        return Err(SpecError::new(
+                            arg.position,
+                            format!("{} takes {} argument(s) at most", self.name, positional),
+                        ));
+                    }
+                }
+                Some(ref key) => {
+                    if !options
+                        .iter()
+                        .any(|option| option.eq_ignore_ascii_case(key))
+                    {
+                        return Err(SpecError::new(
+                            arg.position,
+                            format!("unknown option {:?} for {}", key, self.name),
+                        ));
+                    }
+                    if seen.iter().any(|k| k.eq_ignore_ascii_case(key)) {
+                        return Err(SpecError::new(
+                            arg.position,
+                            format!("option {:?} given twice", key),
+                        ));
+                    }
+                    seen.push(key);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+///Writes the spec in its canonical form: the name, then every value quoted, ex: `or("#", color="red")`<br>
+impl Display for MaskSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+        if self.args.is_empty() {
+            return Ok(());
+        }
+
+        f.write_char('(')?;
+        for (i, arg) in self.args.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            if let Some(ref key) = arg.key {
+                write!(f, "{}=", key)?;
+            }
+            quote(f, &arg.value)?;
+        }
+        f.write_char(')')
+    }
+}
+
+fn quote(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for ch in value.chars() {
+        match ch {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            '\0' => f.write_str("\\0")?,
+            ch if ch.is_control() => write!(f, "\\u{{{:x}}}", ch as u32)?,
+            ch => f.write_char(ch)?,
+        }
+    }
+    f.write_char('"')
+}
+
+impl FromStr for MaskSpec {
+    type Err = SpecError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Parser {
+            chars: s.chars().collect(),
+            at: 0,
+        }
+        .spec()
+    }
+}
+
+///Parses `s` as a spec for the mask M, see FromSpec<br>
+pub fn parse<M: FromSpec>(s: &str) -> Result<M, SpecError> {
+    let spec: MaskSpec = s.parse()?;
+    if !spec.is(M::NAMES) {
+        return Err(SpecError::new(
+            spec.position,
+            format!("expected a {} mask, found {:?}", M::NAMES[0], spec.name),
+        ));
+    }
+
+    spec.expect(M::ARGS.len(), M::OPTIONS)?;
+    M::from_spec(&spec)
+}
+
+///Masks that can be built from a spec<br>
+///The constants describe the mask for listings (see MaskRegistry), and the arguments are checked against ARGS and OPTIONS before from_spec is called<br>
+pub trait FromSpec: Sized {
+    ///Names the mask goes by, the first one is the one it displays as<br>
+    const NAMES: &'static [&'static str];
+
+    ///Names of the positional arguments, all of them optional<br>
+    const ARGS: &'static [&'static str] = &[];
+
+    ///Keys of the `key=value` options<br>
+    const OPTIONS: &'static [&'static str] = &[];
+
+    ///One line description<br>
+    const DESCRIPTION: &'static str = "";
+
+    ///Builds the mask from a spec naming it, with arguments that match ARGS and OPTIONS<br>
+    fn from_spec(spec: &MaskSpec) -> Result<Self, SpecError>;
+}
+
+///Applies the `color` option of a spec to a mask, if given<br>
+///Without the `colored` feature the option is accepted and ignored, so the same spec works with every build<br>
+pub(crate) fn apply_color<M: crate::render::RenderMask>(
+    spec: &MaskSpec,
+    _mask: &mut M,
+) -> Result<(), SpecError> {
+    #[cfg(feature = "colored")]
+    if let Some(arg) = spec.get("color") {
+        arg.value
+            .parse::<crate::colors::Style>()
+            .map_err(|e| SpecError::new(arg.position, e))?;
+        crate::render::RenderMask::set_color(_mask, &arg.value);
+    }
+
+    #[cfg(not(feature = "colored"))]
+    let _ = spec;
+
+    Ok(())
+}
+
+struct Parser {
+    chars: Vec<char>,
+    at: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.at).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.at += 1;
+        }
+    }
+
+    fn spec(mut self) -> Result<MaskSpec, SpecError> {
+        self.skip_whitespace();
+        let position = self.at;
+
+        let mut name = String::new();
+        while let Some(ch) = self
+            .peek()
+            .filter(|ch| ch.is_alphanumeric() || *ch == '_' || *ch == '-')
+        {
+            name.push(ch);
+            self.at += 1;
+        }
+        if name.is_empty() {
+            return Err(self.unexpected("a mask name"));
+        }
+
+        let mut spec = MaskSpec {
+            name,
+            args: Vec::new(),
+            position,
+        };
+
+        self.skip_whitespace();
+        let close = match self.peek() {
+            Some('(') => Some(')'),
+            Some('[') => Some(']'),
+            Some('{') => Some('}'),
+            _ => None,
+        };
+
+        if let Some(close) = close {
+            self.at += 1;
+            spec.args = self.args(close)?;
+            self.skip_whitespace();
+        }
+
+        match self.peek() {
+            None => Ok(spec),
+            Some(_) => Err(self.unexpected("the end of the mask")),
+        }
+    }
+
+    fn args(&mut self, close: char) -> Result<Vec<Arg>, SpecError> {
+        let open = self.at - 1;
+        let mut args = Vec::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some(close) {
+            self.at += 1;
+            return Ok(args);
+        }
+
+        loop {
+            self.skip_whitespace();
+            let position = self.at;
+            let (first, quoted) = self.value(close)?;
+
+            self.skip_whitespace();
+            let arg = match self.peek() {
+                Some('=') if !quoted => {
+                    if !is_key(&first) {
+                        return Err(SpecError::new(
+                            position,
+                            format!("invalid option name {:?}", first),
+                        ));
+                    }
+                    self.at += 1;
+                    self.skip_whitespace();
+                    let (value, _) = self.value(close)?;
+                    self.skip_whitespace();
+                    Arg {
+                        key: Some(first),
+                        value,
+                        position,
+                    }
+                }
+                _ => Arg {
+                    key: None,
+                    value: first,
+                    position,
+                },
+            };
+            args.push(arg);
+
+            match self.peek() {
+                Some(',') => self.at += 1,
+                Some(ch) if ch == close => {
+                    self.at += 1;
+                    return Ok(args);
+                }
+                Some(_) => return Err(self.unexpected(&format!("',' or '{}'", close))),
+                None => {
+                    return Err(SpecError::new(
+                        open,
+                        format!("unclosed '{}'", self.chars[open]),
+                    ))
+                }
+            }
+        }
+    }
+
+    ///A quoted or bare value, and whether it was quoted<br>
+    fn value(&mut self, close: char) -> Result<(String, bool), SpecError> {
+        match self.peek() {
+            Some(quote @ ('"' | '\'')) => self.quoted(quote).map(|value| (value, true)),
+            _ => self.bare(close).map(|value| (value, false)),
+        }
+    }
+
+    fn bare(&mut self, close: char) -> Result<String, SpecError> {
+        let mut value = String::new();
+        //Trailing whitespace is dropped, unless it was escaped
+        let mut kept = 0;
+
+        while let Some(ch) = self.peek() {
+            if ch == ',' || ch == '=' || ch == close {
+                break;
+            }
+            self.at += 1;
+
+            match ch {
+                '\\' => match self.peek() {
+                    Some(escaped) => {
+                        self.at += 1;
+                        value.push(escaped);
+                        kept = value.len();
+                    }
+                    None => return Err(SpecError::new(self.at - 1, "nothing to escape")),
+                },
+                ch => {
+                    value.push(ch);
+                    if !ch.is_whitespace() {
+                        kept = value.len();
+                    }
+                }
+            }
+        }
+
+        value.truncate(kept);
+        Ok(value)
+    }
+
+    fn quoted(&mut self, quote: char) -> Result<String, SpecError> {
+        let open = self.at;
+        self.at += 1;
+        let mut value = String::new();
+
+        loop {
+            let ch = match self.peek() {
+                Some(ch) => ch,
+                None => return Err(SpecError::new(open, "unterminated string")),
+            };
+            self.at += 1;
+
+            match ch {
+                ch if ch == quote => return Ok(value),
+                '\\' => value.push(self.escape()?),
+                ch => value.push(ch),
+            }
+        }
+    }
+
+    ///The character for the escape right after a `\`<br>
+    fn escape(&mut self) -> Result<char, SpecError> {
+        let position = self.at - 1;
+        let ch = self.peek();
+        self.at += 1;
+
+        match ch {
+            Some('n') => Ok('\n'),
+            Some('r') => Ok('\r'),
+            Some('t') => Ok('\t'),
+            Some('0') => Ok('\0'),
+            Some(ch @ ('\\' | '"' | '\'')) => Ok(ch),
+            Some('u') => {
+                if self.peek() != Some('{') {
+                    return Err(SpecError::new(position, "expected '{' after \\u"));
+                }
+                self.at += 1;
+
+                let mut digits = String::new();
+                while let Some(ch) = self.peek().filter(|ch| *ch != '}') {
+                    digits.push(ch);
+                    self.at += 1;
+                }
+                if self.peek().is_none() {
+                    return Err(SpecError::new(position, "unterminated \\u escape"));
+                }
+                self.at += 1;
+
+                u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| {
+                        SpecError::new(position, format!("invalid unicode escape {:?}", digits))
+                    })
+            }
+            Some(ch) => Err(SpecError::new(position, format!("unknown escape \\{}", ch))),
+            None => Err(SpecError::new(position, "unterminated string")),
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> SpecError {
+        match self.peek() {
+            Some(ch) => SpecError::new(self.at, format!("expected {}, found {:?}", expected, ch)),
+            None => SpecError::new(self.at, format!("expected {}, found the end", expected)),
+        }
+    }
+}
+
+fn is_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|ch| ch.is_alphanumeric() || ch == '_' || ch == '-')
+}
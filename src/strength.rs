@@ -0,0 +1,234 @@
+//!Password strength meter mask<br>
+//!Masks the password like Standard, and shows a bar with a weak/fair/strong label after it that updates on every keystroke<br>
+//!The estimate is based on character classes and length, with sequences (abc, 321) and repeats (aaa) counting for very little<br>
+//!The mask is only available if the `strength` feature flag is enabled<br>
+
+use crate::masks::DEFAULT_MASK_CHAR;
+use crate::render::{display_width, Glyph, Line, RenderMask};
+use crate::spec::{self, FromSpec, MaskSpec, SpecError};
+use crate::EditBuffer;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+use std::sync::Arc;
+
+#[cfg(feature = "colored")]
+use crate::colors;
+
+///Strength levels shown by the meter<br>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Weak,
+    Fair,
+    Strong,
+}
+
+impl Level {
+    ///Level for an entropy estimate in bits<br>
+    pub fn from_bits(bits: f64) -> Self {
+        match bits {
+            b if b < 40.0 => Level::Weak,
+            b if b < 64.0 => Level::Fair,
+            _ => Level::Strong,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Level::Weak => "weak",
+            Level::Fair => "fair",
+            Level::Strong => "strong",
+        }
+    }
+
+    ///Default color of the meter bar at this level<br>
+    #[cfg(feature = "colored")]
+    fn color(&self) -> &'static str {
+        match self {
+            Level::Weak => "red",
+            Level::Fair => "yellow",
+            Level::Strong => "green",
+        }
+    }
+}
+
+///Rough entropy estimate of a password in bits<br>
+///Every character is worth log2 of the size of the character classes used in the password, except for characters that repeat or continue a sequence from the one before, which are worth 1 bit<br>
+pub fn estimate(password: &str) -> f64 {
+    let (mut lower, mut upper, mut digit, mut symbol, mut other) = (false, false, false, false, false);
+
+    for ch in password.chars() {
+        match ch {
+            'a'..='z' => lower = true,
+            'A'..='Z' => upper = true,
+            '0'..='9' => digit = true,
+            ch if ch.is_ascii() => symbol = true,
+            _ => other = true,
+        }
+    }
+
+    let pool = [(lower, 26), (upper, 26), (digit, 10), (symbol, 33), (other, 100)]
+        .iter()
+        .filter(|(used, _)| *used)
+        .map(|(_, size)| size)
+        .sum::<u32>();
+
+    if pool == 0 {
+        return 0.0;
+    }
+
+    let bits = f64::from(pool).log2();
+    let mut previous: Option<char> = None;
+
+    password
+        .chars()
+        .map(|ch| {
+            let predictable = match previous {
+                Some(p) => (ch as i64 - p as i64).abs() <= 1,
+                None => false,
+            };
+            previous = Some(ch);
+
+            match predictable {
+                true => 1.0,
+                false => bits,
+            }
+        })
+        .sum()
+}
+
+pub const DEFAULT_METER_WIDTH: usize = 10;
+
+///Mask with a live strength meter<br>
+///Ex: `*********  [######    ] fair`<br>
+pub struct StrengthMeter {
+    mask: Arc<str>,
+    bar_width: usize,
+
+    #[cfg(feature = "colored")]
+    color: Arc<str>,
+
+    ///Styles of the bar, weak to strong<br>
+    #[cfg(feature = "colored")]
+    levels: [Arc<str>; 3],
+}
+
+impl StrengthMeter {
+    pub fn new(mask: &str) -> Self {
+        Self {
+            mask: Arc::from(mask),
+            bar_width: DEFAULT_METER_WIDTH,
+
+            #[cfg(feature = "colored")]
+            color: Arc::from(colors::DEFAULT_MASK_COLOR),
+            #[cfg(feature = "colored")]
+            levels: [Level::Weak, Level::Fair, Level::Strong].map(|level| Arc::from(level.color())),
+        }
+    }
+
+    ///Number of cells taken by the bar itself<br>
+    pub fn set_bar_width(&mut self, width: usize) {
+        self.bar_width = width;
+    }
+
+    fn meter(&self, password: &EditBuffer) -> Glyph {
+        let bits = estimate(password.expose());
+        let level = Level::from_bits(bits);
+
+        //The bar fills up at 80 bits
+        let filled = ((bits / 80.0) * self.bar_width as f64).ceil() as usize;
+        let filled = filled.min(self.bar_width);
+        let bar = format!(
+            "{}{}",
+            "#".repeat(filled),
+            " ".repeat(self.bar_width - filled)
+        );
+
+        #[cfg(feature = "colored")]
+        let bar = colors::paint(&bar, &self.levels[level as usize]);
+
+        let text = format!("  [{}] {:<6}", bar, level.label());
+        Glyph::new(text, self.bar_width + 11)
+    }
+
+    fn glyphs(&self, password: &EditBuffer, meter: bool) -> Vec<Glyph> {
+        let mask = self.mask.to_string();
+
+        #[cfg(feature = "colored")]
+        let mask = colors::paint(&mask, &self.color);
+
+        let mut glyphs = vec![Glyph::new(mask, display_width(&self.mask)); password.len()];
+        if meter && !password.is_empty() {
+            glyphs.push(self.meter(password));
+        }
+        glyphs
+    }
+}
+
+impl RenderMask for StrengthMeter {
+    fn default_mask() -> Self {
+        Self::new(DEFAULT_MASK_CHAR)
+    }
+
+    ///The meter is cleared once the password has been entered<br>
+    fn render(&self, password: &EditBuffer, finished: bool) -> Line {
+        Line::new(self.glyphs(password, !finished), password.cursor())
+    }
+
+    #[cfg(feature = "colored")]
+    fn set_color(&mut self, c: &str) {
+        self.color = Arc::from(c);
+    }
+
+    #[cfg(feature = "colored")]
+    fn set_theme(&mut self, theme: &colors::Theme) {
+        self.color = Arc::from(theme.mask.as_str());
+        self.levels = [&theme.weak, &theme.fair, &theme.strong].map(|style| Arc::from(style.as_str()));
+    }
+}
+
+///`strength` or `meter`, with an optional mask, and `width` (of the bar) and `color` options<br>
+impl FromSpec for StrengthMeter {
+    const NAMES: &'static [&'static str] = &["strength", "meter"];
+    const ARGS: &'static [&'static str] = &["mask"];
+    const OPTIONS: &'static [&'static str] = &["width", "color"];
+    const DESCRIPTION: &'static str = "Masked, followed by a live strength meter";
+
+    fn from_spec(spec: &MaskSpec) -> Result<Self, SpecError> {
+        let mut mask = match spec.positional(0) {
+            Some(arg) => Self::new(&arg.value),
+            None => Self::default_mask(),
+        };
+
+        if let Some(arg) = spec.get("width") {
+            let width = arg.value.parse().map_err(|_| {
+                SpecError::new(arg.position, format!("invalid width {:?}", arg.value))
+            })?;
+            mask.set_bar_width(width);
+        }
+
+        spec::apply_color(spec, &mut mask)?;
+        Ok(mask)
+    }
+}
+
+impl FromStr for StrengthMeter {
+    type Err = SpecError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        spec::parse(s)
+    }
+}
+
+impl Display for StrengthMeter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut spec = MaskSpec::new(Self::NAMES[0]).arg(&self.mask);
+        if self.bar_width != DEFAULT_METER_WIDTH {
+            spec = spec.option("width", self.bar_width);
+        }
+
+        #[cfg(feature = "colored")]
+        let spec = spec.color(&self.color);
+
+        spec.fmt(f)
+    }
+}
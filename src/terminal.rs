@@ -0,0 +1,303 @@
+//!Terminal handling for unix terminals<br>
+//!TerminalGuard switches off line buffering and echo, turns on bracketed paste, and puts everything back the way it was on every exit path: drop, panics and fatal signals<br>
+//!Ctrl-Z keeps working: the terminal is restored while the process is stopped, and set up again once it is resumed with `fg`<br>
+//!Resizes (SIGWINCH) are picked up as well, so the prompt can be drawn again for the new width<br>
+
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::os::unix::io::RawFd;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::Once;
+
+const PASTE_ON: &[u8] = b"\x1b[?2004h";
+const PASTE_OFF: &[u8] = b"\x1b[?2004l";
+
+///Signals after which the terminal is restored, before passing the signal on<br>
+const FATAL: [libc::c_int; 4] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP, libc::SIGQUIT];
+///Signals handled while the guard is active, besides FATAL<br>
+const OTHER: [libc::c_int; 2] = [libc::SIGTSTP, libc::SIGWINCH];
+const SIGNALS: usize = FATAL.len() + OTHER.len();
+
+struct State {
+    original: libc::termios,
+    raw: libc::termios,
+    ///`/dev/tty` opened for writing the bracketed paste switches, -1 if it could not be opened<br>
+    tty: libc::c_int,
+    ///Handlers in place before the guard, FATAL followed by OTHER<br>
+    previous: [libc::sigaction; SIGNALS],
+}
+
+//Written only while no guard is active, read by the signal handlers and panic hook only while one is
+struct Shared(UnsafeCell<MaybeUninit<State>>);
+unsafe impl Sync for Shared {}
+
+static STATE: Shared = Shared(UnsafeCell::new(MaybeUninit::uninit()));
+static CLAIMED: AtomicBool = AtomicBool::new(false);
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static REDRAW: AtomicBool = AtomicBool::new(false);
+static PANIC_HOOK: Once = Once::new();
+
+///Pipe written to whenever REDRAW is set, see redraw_fd<br>
+///Opened once for the whole process and never closed, so a signal handler never writes to a descriptor that went away<br>
+static REDRAW_PIPE: Once = Once::new();
+static REDRAW_READ: AtomicI32 = AtomicI32::new(-1);
+static REDRAW_WRITE: AtomicI32 = AtomicI32::new(-1);
+
+///Guard holding the terminal on stdin in no-echo, non-canonical, bracketed paste mode<br>
+///Only one guard exists at a time, GPass::get_password holds one while reading from Getch<br>
+///The original settings are restored when the guard is dropped, when a panic happens (even with `panic = "abort"`), and on SIGINT/SIGTERM/SIGHUP/SIGQUIT, which are then passed on to the handlers that were there before<br>
+///If such a handler returns instead of exiting, the terminal is set up again and the prompt drawn again, so nothing typed afterwards is echoed<br>
+pub struct TerminalGuard {
+    _private: (),
+}
+
+impl TerminalGuard {
+    ///Take over the terminal on stdin<br>
+    ///Ok(None) if stdin is not a terminal, or if another guard already holds it<br>
+    pub fn acquire() -> std::io::Result<Option<Self>> {
+        if unsafe { libc::isatty(libc::STDIN_FILENO) } != 1 {
+            return Ok(None);
+        }
+
+        if CLAIMED.swap(true, Ordering::SeqCst) {
+            return Ok(None);
+        }
+
+        match unsafe { Self::setup() } {
+            Ok(guard) => Ok(Some(guard)),
+            Err(e) => {
+                CLAIMED.store(false, Ordering::SeqCst);
+                Err(e)
+            }
+        }
+    }
+
+    unsafe fn setup() -> std::io::Result<Self> {
+        let mut original: libc::termios = std::mem::zeroed();
+        if libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        let mut raw = original;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+
+        let tty = libc::open(
+            c"/dev/tty".as_ptr(),
+            libc::O_WRONLY | libc::O_NOCTTY | libc::O_CLOEXEC,
+        );
+
+        if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, &raw) != 0 {
+            let e = std::io::Error::last_os_error();
+            if tty >= 0 {
+                libc::close(tty);
+            }
+            return Err(e);
+        }
+        write_tty(tty, PASTE_ON);
+
+        let state = (*STATE.0.get()).write(State {
+            original,
+            raw,
+            tty,
+            previous: std::mem::zeroed(),
+        });
+
+        install_panic_hook();
+        open_redraw_pipe();
+        ACTIVE.store(true, Ordering::SeqCst);
+
+        for (i, signal) in FATAL.iter().enumerate() {
+            catch(*signal, on_fatal, &mut state.previous[i]);
+        }
+        catch(libc::SIGTSTP, on_stop, &mut state.previous[FATAL.len()]);
+        catch(libc::SIGWINCH, on_resize, &mut state.previous[FATAL.len() + 1]);
+
+        Ok(Self { _private: () })
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        unsafe {
+            let state = (*STATE.0.get()).assume_init_ref();
+            let signals = FATAL.iter().chain(&OTHER);
+            for (signal, previous) in signals.zip(&state.previous) {
+                libc::sigaction(*signal, previous, std::ptr::null_mut());
+            }
+
+            ACTIVE.store(false, Ordering::SeqCst);
+            leave(state);
+            if state.tty >= 0 {
+                libc::close(state.tty);
+            }
+        }
+
+        REDRAW.store(false, Ordering::SeqCst);
+        CLAIMED.store(false, Ordering::SeqCst);
+    }
+}
+
+///Whether the process has been resumed (Ctrl-Z, then `fg`) or the terminal resized since the last call<br>
+///Input streams use this to ask for the prompt to be drawn again, see InputToken::Redraw<br>
+pub fn take_redraw() -> bool {
+    REDRAW.swap(false, Ordering::SeqCst)
+}
+
+///A non-blocking descriptor that becomes readable when a redraw is asked for, -1 if it could not be opened<br>
+///For readers that wait on more than the terminal, so they can pick up take_redraw without waiting for the next key (see async_input). They read it empty themselves<br>
+pub(crate) fn redraw_fd() -> RawFd {
+    open_redraw_pipe();
+    REDRAW_READ.load(Ordering::SeqCst)
+}
+
+fn open_redraw_pipe() {
+    REDRAW_PIPE.call_once(|| unsafe {
+        let mut fds = [-1; 2];
+        if libc::pipe(fds.as_mut_ptr()) != 0 {
+            return;
+        }
+        for fd in fds {
+            libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC);
+            libc::fcntl(fd, libc::F_SETFL, libc::fcntl(fd, libc::F_GETFL) | libc::O_NONBLOCK);
+        }
+        REDRAW_READ.store(fds[0], Ordering::SeqCst);
+        REDRAW_WRITE.store(fds[1], Ordering::SeqCst);
+    });
+}
+
+///Number of columns of the terminal held by the active guard, None if no guard is active<br>
+pub fn columns() -> Option<usize> {
+    if !ACTIVE.load(Ordering::SeqCst) {
+        return None;
+    }
+
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    match unsafe { libc::ioctl(libc::STDIN_FILENO, libc::TIOCGWINSZ, &mut size) } {
+        0 if size.ws_col > 0 => Some(size.ws_col as usize),
+        _ => None,
+    }
+}
+
+//Everything below may run inside a signal handler, so only async-signal-safe calls are made
+
+unsafe fn write_tty(tty: libc::c_int, bytes: &[u8]) {
+    if tty >= 0 {
+        libc::write(tty, bytes.as_ptr() as *const libc::c_void, bytes.len());
+    }
+}
+
+///Sets REDRAW and wakes up whoever waits on redraw_fd<br>
+unsafe fn request_redraw() {
+    REDRAW.store(true, Ordering::SeqCst);
+
+    //A full pipe is already readable, so a failed write loses nothing
+    let fd = REDRAW_WRITE.load(Ordering::SeqCst);
+    if fd >= 0 {
+        libc::write(fd, b"!".as_ptr() as *const libc::c_void, 1);
+    }
+}
+
+unsafe fn leave(state: &State) {
+    libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &state.original);
+    write_tty(state.tty, PASTE_OFF);
+}
+
+unsafe fn enter(state: &State) {
+    libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &state.raw);
+    write_tty(state.tty, PASTE_ON);
+}
+
+///Install `handler` for `signal`, saving the current handler in `previous`<br>
+///Signals that are being ignored (ex: SIGHUP under nohup) are left alone<br>
+unsafe fn catch(
+    signal: libc::c_int,
+    handler: extern "C" fn(libc::c_int),
+    previous: &mut libc::sigaction,
+) {
+    libc::sigaction(signal, std::ptr::null(), previous);
+    if previous.sa_sigaction == libc::SIG_IGN {
+        return;
+    }
+
+    //No SA_RESTART, so a blocking read returns EINTR and Getch gets to check for a resume or a resize
+    let mut action: libc::sigaction = std::mem::zeroed();
+    action.sa_sigaction = handler as usize;
+    libc::sigemptyset(&mut action.sa_mask);
+    libc::sigaction(signal, &action, std::ptr::null_mut());
+}
+
+extern "C" fn on_fatal(signal: libc::c_int) {
+    unsafe {
+        if !ACTIVE.load(Ordering::SeqCst) {
+            //Delivered to the default handler once this one returns
+            libc::signal(signal, libc::SIG_DFL);
+            libc::raise(signal);
+            return;
+        }
+
+        let state = (*STATE.0.get()).assume_init_ref();
+        leave(state);
+
+        let i = FATAL.iter().position(|s| *s == signal).unwrap_or(0);
+        libc::sigaction(signal, &state.previous[i], std::ptr::null_mut());
+
+        //Delivered right away, so the previous handler has run by the time raise returns
+        let mut set: libc::sigset_t = std::mem::zeroed();
+        libc::sigemptyset(&mut set);
+        libc::sigaddset(&mut set, signal);
+        libc::pthread_sigmask(libc::SIG_UNBLOCK, &set, std::ptr::null_mut());
+        libc::raise(signal);
+
+        //Still running: the application handled the signal without exiting (ex: ctrlc, tokio::signal), so the prompt carries on
+        let mut previous: libc::sigaction = std::mem::zeroed();
+        catch(signal, on_fatal, &mut previous);
+        //Before the terminal is set up again, so a key read as soon as it is comes with the redraw
+        request_redraw();
+        enter(state);
+    }
+}
+
+extern "C" fn on_stop(_signal: libc::c_int) {
+    unsafe {
+        if !ACTIVE.load(Ordering::SeqCst) {
+            return;
+        }
+
+        let state = (*STATE.0.get()).assume_init_ref();
+        leave(state);
+
+        //Stop for real, execution carries on from here after SIGCONT
+        libc::signal(libc::SIGTSTP, libc::SIG_DFL);
+        let mut set: libc::sigset_t = std::mem::zeroed();
+        libc::sigemptyset(&mut set);
+        libc::sigaddset(&mut set, libc::SIGTSTP);
+        libc::pthread_sigmask(libc::SIG_UNBLOCK, &set, std::ptr::null_mut());
+        libc::raise(libc::SIGTSTP);
+
+        let mut default: libc::sigaction = std::mem::zeroed();
+        catch(libc::SIGTSTP, on_stop, &mut default);
+        request_redraw();
+        enter(state);
+    }
+}
+
+extern "C" fn on_resize(_signal: libc::c_int) {
+    if ACTIVE.load(Ordering::SeqCst) {
+        unsafe { request_redraw() };
+    }
+}
+
+///Restores the terminal before the panic message is printed, which also covers `panic = "abort"` where no destructors run<br>
+fn install_panic_hook() {
+    PANIC_HOOK.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if ACTIVE.load(Ordering::SeqCst) {
+                unsafe { leave((*STATE.0.get()).assume_init_ref()) };
+            }
+            previous(info);
+        }));
+    });
+}
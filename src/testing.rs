@@ -0,0 +1,343 @@
+//!Helpers for testing masks and prompts against what the user would actually see<br>
+//!Screen is an in-memory terminal: it takes the output of GPass or a mask, and interprets backspaces, carriage returns, newlines, cursor movement, clear-line and SGR (color and style) codes into a grid of cells<br>
+//!`run` drives a GPass with a script::Script and keeps the visible line after every token<br>
+//!```rust
+//!let gpass = GPass::new(None, Box::new(gpass::script::Script::new()), Box::new(masks::Standard::default()), Box::new(gpass::Void), true);
+//!let run = gpass::testing::run(gpass, "ab<BS><Enter>".parse()?);
+//!assert_eq!(run.frames, ["Enter the Password:", "Enter the Password: *", "Enter the Password: **", "Enter the Password: *", ""]);
+//!```
+
+use crate::script::Script;
+use crate::{GPass, InputStream, InputToken, LibError, Secret};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use unicode_width::UnicodeWidthChar;
+
+///One cell of the screen<br>
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cell {
+    ///What is shown in the cell: one character with any combining marks after it, a space for blanked cells, and empty for the right half of a wide character<br>
+    pub text: String,
+    ///The SGR parameters in effect when the cell was written, ex: `1;31` for bold red, empty for plain text<br>
+    pub sgr: String,
+}
+
+impl Cell {
+    fn blank() -> Self {
+        Cell {
+            text: " ".to_string(),
+            sgr: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Parser {
+    #[default]
+    Ground,
+    Escape,
+    Csi,
+}
+
+#[derive(Debug, Default)]
+struct Grid {
+    rows: Vec<Vec<Cell>>,
+    row: usize,
+    col: usize,
+    sgr: Vec<String>,
+    parser: Parser,
+    params: String,
+    //Bytes of a UTF-8 character split across writes
+    partial: Vec<u8>,
+    bracketed_paste: bool,
+}
+
+impl Grid {
+    fn line(&mut self) -> &mut Vec<Cell> {
+        if self.rows.len() <= self.row {
+            self.rows.resize_with(self.row + 1, Vec::new);
+        }
+        &mut self.rows[self.row]
+    }
+
+    fn put(&mut self, ch: char) {
+        let width = ch.width().unwrap_or(0);
+        let (col, sgr) = (self.col, self.sgr.join(";"));
+
+        if width == 0 {
+            //Combining characters join the cell before them
+            if let Some(cell) = col.checked_sub(1).and_then(|at| self.line().get_mut(at)) {
+                cell.text.push(ch);
+            }
+            return;
+        }
+
+        let line = self.line();
+        if line.len() < col + width {
+            line.resize_with(col + width, Cell::blank);
+        }
+        line[col] = Cell {
+            text: ch.to_string(),
+            sgr: sgr.clone(),
+        };
+        for cell in &mut line[col + 1..col + width] {
+            *cell = Cell {
+                text: String::new(),
+                sgr: sgr.clone(),
+            };
+        }
+        self.col += width;
+    }
+
+    fn control(&mut self, ch: char) {
+        match ch {
+            '\x08' => self.col = self.col.saturating_sub(1),
+            '\r' => self.col = 0,
+            '\n' => {
+                self.row += 1;
+                self.col = 0;
+                self.line();
+            }
+            '\t' => self.col = (self.col / 8 + 1) * 8,
+            '\x1b' => self.parser = Parser::Escape,
+            _ => {}
+        }
+    }
+
+    fn feed(&mut self, ch: char) {
+        match self.parser {
+            Parser::Ground if ch.is_control() => self.control(ch),
+            Parser::Ground => self.put(ch),
+            Parser::Escape => {
+                self.parser = match ch {
+                    '[' => Parser::Csi,
+                    //Other escape sequences (ex: save cursor) are left out
+                    _ => Parser::Ground,
+                };
+                self.params.clear();
+            }
+            Parser::Csi if ('\x40'..='\x7e').contains(&ch) => {
+                self.parser = Parser::Ground;
+                let params = std::mem::take(&mut self.params);
+                self.csi(&params, ch);
+            }
+            Parser::Csi => self.params.push(ch),
+        }
+    }
+
+    fn csi(&mut self, params: &str, action: char) {
+        let n = params.parse::<usize>().unwrap_or(1).max(1);
+
+        match action {
+            'm' => {
+                for param in params.split(';') {
+                    match param.trim_start_matches('0') {
+                        "" => self.sgr.clear(),
+                        param => self.sgr.push(param.to_string()),
+                    }
+                }
+            }
+            'K' => {
+                let col = self.col;
+                let line = self.line();
+                match params {
+                    "1" => line
+                        .iter_mut()
+                        .take(col + 1)
+                        .for_each(|cell| *cell = Cell::blank()),
+                    "2" => line.clear(),
+                    _ => line.truncate(col),
+                }
+            }
+            'J' if params == "2" => {
+                self.rows.clear();
+            }
+            'D' => self.col = self.col.saturating_sub(n),
+            'C' => self.col += n,
+            'G' => self.col = n - 1,
+            'A' => self.row = self.row.saturating_sub(n),
+            'B' => self.row += n,
+            'h' if params == "?2004" => self.bracketed_paste = true,
+            'l' if params == "?2004" => self.bracketed_paste = false,
+            _ => {}
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.partial.extend_from_slice(bytes);
+        let pending = std::mem::take(&mut self.partial);
+        let mut rest = &pending[..];
+
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(text) => {
+                    text.chars().for_each(|ch| self.feed(ch));
+                    return;
+                }
+                Err(e) => {
+                    let (valid, after) = rest.split_at(e.valid_up_to());
+                    //Checked by from_utf8 above
+                    let valid = std::str::from_utf8(valid).unwrap_or_default();
+                    valid.chars().for_each(|ch| self.feed(ch));
+
+                    match e.error_len() {
+                        Some(len) => {
+                            self.feed(char::REPLACEMENT_CHARACTER);
+                            rest = &after[len..];
+                        }
+                        None => {
+                            self.partial = after.to_vec();
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn text(&self, row: usize) -> String {
+        self.rows
+            .get(row)
+            .map(|cells| {
+                cells
+                    .iter()
+                    .map(|cell| cell.text.as_str())
+                    .collect::<String>()
+            })
+            .unwrap_or_default()
+            .trim_end()
+            .to_string()
+    }
+}
+
+///In-memory terminal screen, written to through std::io::Write<br>
+///Clones share the same screen, so one can be handed to GPass as its output stream and the other kept to look at<br>
+///Lines have no width limit and never wrap. Escape sequences other than the ones listed in the module are left out<br>
+#[derive(Debug, Clone, Default)]
+pub struct Screen(Arc<Mutex<Grid>>);
+
+impl Screen {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    fn grid(&self) -> MutexGuard<'_, Grid> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    ///The line the cursor is on, without trailing blanks<br>
+    pub fn line(&self) -> String {
+        let grid = self.grid();
+        grid.text(grid.row)
+    }
+
+    ///Row `row` (from 0), without trailing blanks<br>
+    pub fn row(&self, row: usize) -> String {
+        self.grid().text(row)
+    }
+
+    ///Every row written to, without trailing blanks<br>
+    pub fn lines(&self) -> Vec<String> {
+        let grid = self.grid();
+        (0..grid.rows.len()).map(|row| grid.text(row)).collect()
+    }
+
+    ///Row and column of the cursor<br>
+    pub fn cursor(&self) -> (usize, usize) {
+        let grid = self.grid();
+        (grid.row, grid.col)
+    }
+
+    pub fn cell(&self, row: usize, col: usize) -> Option<Cell> {
+        self.grid().rows.get(row)?.get(col).cloned()
+    }
+
+    ///Whether bracketed paste is turned on<br>
+    pub fn bracketed_paste(&self) -> bool {
+        self.grid().bracketed_paste
+    }
+
+    ///Blanks the screen and puts the cursor back at the top<br>
+    pub fn clear(&self) {
+        *self.grid() = Grid::default();
+    }
+}
+
+impl std::io::Write for Screen {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.grid().write(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl fmt::Display for Screen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.lines().join("\n"))
+    }
+}
+
+///Takes a snapshot of the screen every time the next token is asked for<br>
+struct Frames {
+    script: Script,
+    screen: Screen,
+    frames: Arc<Mutex<Vec<String>>>,
+}
+
+impl Frames {
+    fn snapshot(&self) {
+        self.frames
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(self.screen.line());
+    }
+}
+
+impl InputStream for Frames {
+    fn get_token(&mut self) -> Result<InputToken, LibError> {
+        self.snapshot();
+        self.script.get_token()
+    }
+
+    fn get_token_timeout(&mut self, timeout: std::time::Duration) -> Result<InputToken, LibError> {
+        self.snapshot();
+        self.script.get_token_timeout(timeout)
+    }
+}
+
+///What a run of GPass left behind<br>
+pub struct Run {
+    pub result: Result<Secret, LibError>,
+    ///The line the cursor was on before the first token and after every token<br>
+    ///After the token ending the input, the cursor has usually moved to the next (empty) line, see `screen` for the rest<br>
+    pub frames: Vec<String>,
+    pub screen: Screen,
+}
+
+///Runs `gpass` with `script` as its input and a fresh Screen as its output<br>
+///The screen is not a terminal, so colors are only drawn with ColorChoice::Always<br>
+pub fn run(mut gpass: GPass, script: Script) -> Run {
+    let screen = Screen::new();
+    let frames = Arc::new(Mutex::new(Vec::new()));
+
+    gpass.input_stream = Box::new(Frames {
+        script,
+        screen: screen.clone(),
+        frames: frames.clone(),
+    });
+    gpass.output_stream = Box::new(screen.clone());
+    gpass.output_is_terminal = false;
+
+    let result = gpass.get_password();
+    let mut frames = std::mem::take(&mut *frames.lock().unwrap_or_else(PoisonError::into_inner));
+    frames.push(screen.line());
+
+    Run {
+        result,
+        frames,
+        screen,
+    }
+}
@@ -29,21 +29,19 @@ fn test_input_functionality() -> Result<(), Box<dyn std::error::Error>> {
     );
 
     let password2 = gpass.get_password()?;
-    assert_eq!(password, password2);
+    assert_eq!(password, password2.expose());
 
     Ok(())
 }
 
 #[test]
 fn test_input_file() -> Result<(), Box<dyn std::error::Error>> {
-    use std::io::BufReader;
-
     //Write input.txt with "Hello World!";
     let file = "input.txt";
     let password = "Hello World!";
     std::fs::write(file, password)?;
 
-    let input_stream = BufReader::new(std::fs::File::open(file)?);
+    let input_stream = ReaderInput::new(std::fs::File::open(file)?);
 
     let gpass = GPass::new(
         Some("Enter Password Hello World! Here: "),
@@ -54,6 +52,1466 @@ fn test_input_file() -> Result<(), Box<dyn std::error::Error>> {
     );
 
     let password2 = gpass.get_password()?;
-    assert_eq!(password, password2);
+    assert_eq!(password, password2.expose());
+    Ok(())
+}
+
+///Replays mask output on a testing::Screen, and returns the line it was drawn on without the colors<br>
+fn screen(output: &[u8]) -> String {
+    use std::io::Write;
+
+    let mut screen = testing::Screen::new();
+    //Writing to a Screen never fails
+    let _ = screen.write_all(output);
+    screen.row(0)
+}
+
+#[test]
+fn test_edit_buffer() {
+    let mut buffer = EditBuffer::new();
+    for ch in "helo world".chars() {
+        buffer.insert(ch);
+    }
+
+    buffer.move_cursor(Motion::WordLeft);
+    buffer.move_cursor(Motion::WordLeft);
+    assert_eq!(buffer.cursor(), 0);
+
+    buffer.move_cursor(Motion::WordRight);
+    buffer.move_cursor(Motion::Left);
+    buffer.insert('l');
+    assert_eq!(buffer.expose(), "hello world");
+
+    buffer.move_cursor(Motion::End);
+    assert!(buffer.backspace());
+    assert!(!buffer.delete());
+
+    buffer.move_cursor(Motion::Home);
+    assert!(buffer.delete());
+    assert!(!buffer.backspace());
+    assert_eq!(buffer.expose(), "ello worl");
+}
+
+#[test]
+fn test_mask_cursor_editing() -> Result<(), MaskError> {
+    let mask = masks::Echo::default();
+    let mut password = EditBuffer::new();
+    let mut o = Vec::new();
+
+    for ch in "pasword".chars() {
+        mask.feed_password(&mut password, ch, &mut o)?;
+    }
+    for _ in 0..4 {
+        mask.move_cursor(&mut password, Motion::Left, &mut o)?;
+    }
+    mask.feed_password(&mut password, 's', &mut o)?;
+    mask.move_cursor(&mut password, Motion::End, &mut o)?;
+    mask.move_cursor(&mut password, Motion::Left, &mut o)?;
+    mask.delete_password(&mut password, &mut o)?;
+    mask.pop_password(&mut password, &mut o)?;
+    mask.feed_password(&mut password, 'r', &mut o)?;
+    mask.feed_password(&mut password, 'd', &mut o)?;
+    mask.end_password(&password, &mut o)?;
+
+    assert_eq!(password.expose(), "password");
+    assert_eq!(screen(&o), "password");
+
+    Ok(())
+}
+
+#[cfg(feature = "reverse")]
+#[test]
+fn test_reverse_cursor_editing() -> Result<(), MaskError> {
+    let mask = reverse::MimiReverse::new("#");
+    let mut password = EditBuffer::new();
+    let mut o = Vec::new();
+
+    for ch in "abcd".chars() {
+        mask.feed_password(&mut password, ch, &mut o)?;
+    }
+    assert_eq!(screen(&o), "###d");
+
+    mask.move_cursor(&mut password, Motion::Left, &mut o)?;
+    mask.move_cursor(&mut password, Motion::Left, &mut o)?;
+    assert_eq!(screen(&o), "####");
+
+    mask.pop_password(&mut password, &mut o)?;
+    assert_eq!(screen(&o), "a##");
+
+    mask.feed_password(&mut password, 'x', &mut o)?;
+    assert_eq!(screen(&o), "#x##");
+
+    mask.end_password(&password, &mut o)?;
+    assert_eq!(screen(&o), "####");
+    assert_eq!(password.expose(), "axcd");
+
+    Ok(())
+}
+
+///Runs bytes through a KeyDecoder and collects the tokens, errors are turned into their Debug representation<br>
+fn decode(bytes: &[u8]) -> Vec<String> {
+    let mut decoder = KeyDecoder::new();
+    bytes
+        .iter()
+        .filter_map(|&b| match decoder.push(b) {
+            Ok(token) => token.map(|t| format!("{:?}", t)),
+            Err(e) => Some(format!("{:?}", e)),
+        })
+        .collect()
+}
+
+#[test]
+fn test_escape_sequences() {
+    assert_eq!(
+        decode(b"a\x1b[Db\x1b[C\x1b[H\x1b[F\x1b[3~\x1bOH\x1b[4~"),
+        [
+            "Character('a')",
+            "Left",
+            "Character('b')",
+            "Right",
+            "Home",
+            "End",
+            "Delete",
+            "Home",
+            "End"
+        ]
+    );
+
+    assert_eq!(
+        decode(b"\x1b[1;5D\x1b[1;3C\x1bOP\x1b[15~\x1b[24~\x1bb\x1b\x1b[D"),
+        [
+            "WordLeft",
+            "WordRight",
+            "Function(1)",
+            "Function(5)",
+            "Function(12)",
+            "Alt('b')",
+            "Left"
+        ]
+    );
+
+    //Unknown sequences are dropped, not typed into the password
+    assert_eq!(
+        decode(b"\x1b[A\x1b[2~\x1b[?25h\x03\r"),
+        ["IgnoreToken", "IgnoreToken", "IgnoreToken", "UserInterrupt", "EOF"]
+    );
+
+    //A stray Escape does not swallow the control key after it
+    assert_eq!(decode(b"\x1b\r"), ["EOF"]);
+    assert_eq!(decode(b"a\x1b\x7fb"), ["Character('a')", "Backspace", "Character('b')"]);
+    assert_eq!(decode(b"\x1b\x03"), ["UserInterrupt"]);
+}
+
+#[test]
+fn test_utf8_keystrokes() {
+    assert_eq!(
+        decode("é日😀".as_bytes()),
+        ["Character('é')", "Character('日')", "Character('😀')"]
+    );
+    assert_eq!(decode("\x1bü".as_bytes()), ["Alt('ü')"]);
+
+    let invalid = decode(b"\xffa\xc3(\xe0\x80\x80");
+    assert!(invalid[0].starts_with("InvalidCharacter"));
+    assert_eq!(invalid[1], "Character('a')");
+    assert!(invalid[2].starts_with("InvalidCharacter"));
+    assert!(invalid[3].starts_with("InvalidCharacter"));
+}
+
+#[test]
+fn test_secret() {
+    let mut secret = Secret::with_capacity(2);
+    for ch in "hünter2".chars() {
+        secret.push(ch);
+    }
+    assert_eq!(secret.expose(), "hünter2");
+    assert!(secret.capacity() >= secret.len());
+
+    assert_eq!(secret.remove(1), 'ü');
+    secret.insert(1, 'u');
+    assert_eq!(secret.expose(), "hunter2");
+
+    assert_eq!(format!("{:?}", secret), "Secret([REDACTED])");
+    assert_eq!(format!("{}", secret), "[REDACTED]");
+}
+
+///Input stream typing each entry followed by Enter<br>
+struct Entries(std::collections::VecDeque<InputToken>);
+
+impl Entries {
+    fn new(entries: &[&str]) -> Self {
+        Self(
+            entries
+                .iter()
+                .flat_map(|e| e.chars().map(InputToken::Character).chain([InputToken::EOF]))
+                .collect(),
+        )
+    }
+}
+
+impl InputStream for Entries {
+    fn get_token(&mut self) -> Result<InputToken, LibError> {
+        Ok(self.0.pop_front().unwrap_or(InputToken::EOF))
+    }
+}
+
+#[test]
+fn test_confirm() -> Result<(), Box<dyn std::error::Error>> {
+    let gpass = GPass {
+        input_stream: Box::new(Entries::new(&["hunter2", "hunter3", "hunter2", "hunter2"])),
+        output_stream: Box::new(Void),
+        confirm: Some(Confirm::default()),
+        ..Default::default()
+    };
+    assert_eq!(gpass.get_password()?.expose(), "hunter2");
+
+    let mut gpass = GPass {
+        input_stream: Box::new(Entries::new(&["a", "b", "c", "d"])),
+        output_stream: Box::new(Void),
+        ..Default::default()
+    };
+    gpass.set_confirm(Confirm::new(Some("Again"), None, 2));
+    assert!(matches!(
+        gpass.get_password(),
+        Err(LibError::ConfirmationFailed(2))
+    ));
+
+    assert!(Secret::from("abc".to_string()).ct_eq(&Secret::from("abc".to_string())));
+    assert!(!Secret::from("abc".to_string()).ct_eq(&Secret::from("abcd".to_string())));
+    Ok(())
+}
+
+#[test]
+fn test_policy() -> Result<(), Box<dyn std::error::Error>> {
+    use policy::{Policy, Validator};
+
+    let gpass = GPass {
+        input_stream: Box::new(Entries::new(&["short", "Password12", "longenough1", "Longenough1"])),
+        output_stream: Box::new(Void),
+        policy: Some("min=8, require=upper+digit, ban=password, attempts=4".parse()?),
+        ..Default::default()
+    };
+    assert_eq!(gpass.get_password()?.expose(), "Longenough1");
+
+    let mut gpass = GPass {
+        input_stream: Box::new(Entries::new(&["abc", "abd"])),
+        output_stream: Box::new(Void),
+        ..Default::default()
+    };
+    gpass.set_policy(Policy::new(2).with(|p: &str| match p.ends_with('!') {
+        true => Ok(()),
+        false => Err("Password must end with !".to_string()),
+    }));
+    assert!(matches!(
+        gpass.get_password(),
+        Err(LibError::PolicyViolation(e)) if e == "Password must end with !"
+    ));
+
+    let explanation = "min=4,max=5".parse::<Policy>()?.validate(&Secret::from("abcdef".to_string()));
+    assert_eq!(explanation, Err("Password must be at most 5 characters long".to_string()));
+    assert!("min=four".parse::<Policy>().is_err());
+    assert!("require=emoji".parse::<Policy>().is_err());
+
+    //Empty banned words are left out rather than banning everything
+    let password = |p: &str| Secret::from(p.to_string());
+    assert_eq!("ban=".parse::<Policy>()?.validate(&password("anything")), Ok(()));
+    let policy = "ban=a::b".parse::<Policy>()?;
+    assert_eq!(policy.validate(&password("xyz")), Ok(()));
+    assert!(policy.validate(&password("xbz")).is_err());
+
+    //Lengths count graphemes, not chars
+    assert_eq!("max=2".parse::<Policy>()?.validate(&password("e\u{301}e\u{301}")), Ok(()));
+    Ok(())
+}
+
+#[cfg(feature = "strength")]
+#[test]
+fn test_strength_meter() -> Result<(), MaskError> {
+    use strength::{estimate, Level, StrengthMeter};
+
+    assert_eq!(Level::from_bits(estimate("abcdefgh")), Level::Weak);
+    assert_eq!(Level::from_bits(estimate("aaaaaaaaaaaaaaaaaaaa")), Level::Weak);
+    assert_eq!(Level::from_bits(estimate("Summer2024")), Level::Fair);
+    assert_eq!(Level::from_bits(estimate("correct horse battery staple")), Level::Strong);
+    assert!(estimate("a1b2c3") > estimate("abc123"));
+
+    let mask = StrengthMeter::new("*");
+    let mut password = EditBuffer::new();
+    let mut o = Vec::new();
+
+    for ch in "Summer2024".chars() {
+        mask.feed_password(&mut password, ch, &mut o)?;
+    }
+    assert_eq!(screen(&o), "**********  [#######   ] fair");
+
+    for _ in 0..7 {
+        mask.pop_password(&mut password, &mut o)?;
+    }
+    assert_eq!(screen(&o), "***  [###       ] weak");
+
+    mask.end_password(&password, &mut o)?;
+    assert_eq!(screen(&o), "***");
+    Ok(())
+}
+
+///Input stream that takes `delay` to produce each token<br>
+struct Slow(std::time::Duration, Entries);
+
+impl InputStream for Slow {
+    fn get_token(&mut self) -> Result<InputToken, LibError> {
+        std::thread::sleep(self.0);
+        self.1.get_token()
+    }
+
+    fn get_token_timeout(&mut self, timeout: std::time::Duration) -> Result<InputToken, LibError> {
+        match self.0 > timeout {
+            true => {
+                std::thread::sleep(timeout);
+                Err(LibError::Timeout)
+            }
+            false => self.get_token(),
+        }
+    }
+}
+
+#[test]
+fn test_timeout() -> Result<(), Box<dyn std::error::Error>> {
+    use std::time::Duration;
+
+    let gpass = GPass {
+        input_stream: Box::new(Slow(Duration::from_millis(1), Entries::new(&["quick"]))),
+        output_stream: Box::new(Void),
+        timeout: Some(Timeout::idle(Duration::from_millis(200))),
+        ..Default::default()
+    };
+    assert_eq!(gpass.get_password()?.expose(), "quick");
+
+    let mut gpass = GPass {
+        input_stream: Box::new(Slow(Duration::from_millis(50), Entries::new(&["slow"]))),
+        output_stream: Box::new(Void),
+        ..Default::default()
+    };
+    gpass.set_timeout(Timeout::idle(Duration::from_millis(10)));
+    assert!(matches!(gpass.get_password(), Err(LibError::Timeout)));
+
+    let gpass = GPass {
+        input_stream: Box::new(Slow(Duration::from_millis(5), Entries::new(&["a".repeat(100).as_str()]))),
+        output_stream: Box::new(Void),
+        timeout: Some(Timeout {
+            idle: Some(Duration::from_millis(200)),
+            total: Some(Duration::from_millis(50)),
+        }),
+        ..Default::default()
+    };
+    assert!(matches!(gpass.get_password(), Err(LibError::Timeout)));
+    Ok(())
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn test_get_password_async() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::async_input::TokioInput;
+    use std::time::Duration;
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()?;
+
+    let password = runtime.block_on(async {
+        let gpass = GPass {
+            output_stream: Box::new(Void),
+            confirm: Some(Confirm::default()),
+            ..Default::default()
+        };
+        gpass
+            .get_password_async(TokioInput::new(&b"hunter2\nhunter22\x7f\r"[..]))
+            .await
+    })?;
+    assert_eq!(password.expose(), "hunter2");
+
+    //Nothing is ever written to the other end of the pipe
+    let (_writer, reader) = tokio::io::duplex(16);
+    let result = runtime.block_on(async {
+        let mut gpass = GPass {
+            output_stream: Box::new(Void),
+            ..Default::default()
+        };
+        gpass.set_timeout(Timeout::total(Duration::from_millis(20)));
+        gpass.get_password_async(TokioInput::new(reader)).await
+    });
+    assert!(matches!(result, Err(LibError::Timeout)));
+
+    //Policy retries and confirmation go through the same steps as get_password, and draw the same screen
+    let gpass = || -> Result<GPass, Box<dyn std::error::Error>> {
+        Ok(GPass {
+            confirm: Some(Confirm::default()),
+            policy: Some("min=4".parse()?),
+            output_is_terminal: false,
+            ..Default::default()
+        })
+    };
+    let sync = testing::run(
+        gpass()?,
+        "ab<Enter>hunter2<Enter>hunter3<Enter>hunter2<Enter>hunter2<Enter>".parse()?,
+    );
+    let screen = testing::Screen::new();
+    let password = runtime.block_on(async {
+        let gpass = GPass {
+            output_stream: Box::new(screen.clone()),
+            ..gpass()?
+        };
+        let input = TokioInput::new(&b"ab\rhunter2\rhunter3\rhunter2\rhunter2\r"[..]);
+        Ok::<_, Box<dyn std::error::Error>>(gpass.get_password_async(input).await?)
+    })?;
+    assert_eq!(password.expose(), sync.result?.expose());
+    assert_eq!(screen.lines(), sync.screen.lines());
+    assert_eq!(screen.lines().len(), 8);
+
+    //Outside of a runtime, stdin is an error rather than a panic
+    assert!(matches!(TokioInput::stdin(), Err(LibError::ForeignLibrary(_))));
+    Ok(())
+}
+
+#[test]
+fn test_paste() -> Result<(), Box<dyn std::error::Error>> {
+    //Newlines and escape sequences inside a paste are part of the text, not keys
+    assert_eq!(
+        decode(b"a\x1b[200~b\nc\x1b[D\x1b[201~\r"),
+        ["Character('a')", "Paste(\"b\\nc\\u{1b}[D\")", "EOF"]
+    );
+
+    let tokens = |paste: &str| {
+        Entries(
+            [
+                InputToken::Character('a'),
+                InputToken::Paste(paste.to_string()),
+                InputToken::Character('z'),
+                InputToken::EOF,
+            ]
+            .into_iter()
+            .collect(),
+        )
+    };
+
+    let gpass = GPass {
+        input_stream: Box::new(tokens("secret\r\n")),
+        output_stream: Box::new(Void),
+        ..Default::default()
+    };
+    assert_eq!(gpass.get_password()?.expose(), "asecretz");
+
+    let mut gpass = GPass {
+        input_stream: Box::new(tokens("secret\n")),
+        output_stream: Box::new(Void),
+        ..Default::default()
+    };
+    gpass.set_paste_policy(PastePolicy::Allow);
+    assert_eq!(gpass.get_password()?.expose(), "asecret\nz");
+
+    //A rejected paste is dropped, the entry so far is kept and drawn again below the message
+    let mut gpass = GPass::new(None, Box::new(script::Script::new()), Box::new(masks::Standard::default()), Box::new(Void), true);
+    gpass.set_paste_policy("reject:no pasting".parse()?);
+    let run = testing::run(gpass, "a<Paste:secret>z<Enter>".parse()?);
+    assert_eq!(run.result?.expose(), "az");
+    assert_eq!(run.screen.lines(), ["Enter the Password: *", "no pasting", "Enter the Password: **", ""]);
+
+    //Pastes too long to be a password are dropped, the end of the paste is still found
+    let long = [&b"a\x1b[200~"[..], &[b'x'; 5000], b"\x1b[201~b"].concat();
+    assert_eq!(decode(&long), ["Character('a')", "IgnoreToken", "Character('b')"]);
+    let longest = decode(&[&b"\x1b[200~"[..], &[b'x'; 4096], b"\x1b[201~"].concat());
+    assert!(longest[0].starts_with("Paste(\"xxx"));
+
+    assert_eq!("strip".parse::<PastePolicy>()?, PastePolicy::StripNewline);
+    assert!("paste".parse::<PastePolicy>().is_err());
+    Ok(())
+}
+
+#[test]
+fn test_redraw() -> Result<(), Box<dyn std::error::Error>> {
+    let mask = masks::Echo::default();
+    let mut password = EditBuffer::new();
+    for ch in "password".chars() {
+        password.insert(ch);
+    }
+    password.move_cursor(Motion::WordLeft);
+    password.move_cursor(Motion::Right);
+
+    //The whole password is drawn, then the cursor is put back where it was
+    let mut o = Vec::new();
+    mask.redraw_password(&password, &mut o)?;
+    assert_eq!(screen(&o), "password");
+    assert!(o.ends_with(&[b'\x08'; 7]) && !o.ends_with(&[b'\x08'; 8]));
+
+    //Resuming after Ctrl-Z in the middle of the entry keeps what was typed
+    let gpass = GPass {
+        input_stream: Box::new(Entries(
+            [
+                InputToken::Character('a'),
+                InputToken::Redraw,
+                InputToken::Character('b'),
+                InputToken::EOF,
+            ]
+            .into_iter()
+            .collect(),
+        )),
+        output_stream: Box::new(Void),
+        ..Default::default()
+    };
+    assert_eq!(gpass.get_password()?.expose(), "ab");
+    Ok(())
+}
+
+#[test]
+fn test_render_mask() -> Result<(), MaskError> {
+    use crate::render::{draw, Glyph, Line, RenderMask};
+
+    //Shows the number of characters typed instead of the password
+    struct Counter;
+
+    impl RenderMask for Counter {
+        fn default_mask() -> Self {
+            Counter
+        }
+
+        fn render(&self, password: &EditBuffer, finished: bool) -> Line {
+            let text = match finished {
+                true => "ok".to_string(),
+                false => format!("({})", password.len()),
+            };
+            let width = text.chars().count();
+            Line::new(vec![Glyph::new(text, width)], 1)
+        }
+
+        #[cfg(feature = "colored")]
+        fn set_color(&mut self, _c: &str) {}
+    }
+
+    let mask = Counter;
+    let mut password = EditBuffer::new();
+    let mut o = Vec::new();
+
+    for ch in "0123456789".chars() {
+        mask.feed_password(&mut password, ch, &mut o)?;
+    }
+    mask.pop_password(&mut password, &mut o)?;
+    assert_eq!(screen(&o), "(9)");
+    mask.end_password(&password, &mut o)?;
+    assert_eq!(screen(&o), "ok");
+
+    //Cells are counted per glyph, not per byte
+    let mask = masks::Standard::new("●");
+    let (mut password, mut o) = (EditBuffer::new(), Vec::new());
+    for ch in "abc".chars() {
+        mask.feed_password(&mut password, ch, &mut o)?;
+    }
+    mask.pop_password(&mut password, &mut o)?;
+    assert_eq!(screen(&o), "●●");
+
+    let before = Line::new(vec![Glyph::new("ab", 2)], 1);
+    let mut o = Vec::new();
+    draw(&mut o, &before, &Line::default())?;
+    assert_eq!(o, b"\x08\x08  \x08\x08");
+
+    //Cleartext glyphs take the string over (it is wiped with the glyph), painting does not leave a grown buffer behind
+    let text = "é".to_string();
+    let at = text.as_ptr();
+    assert_eq!(Glyph::cleartext(text, 1).text().as_ptr(), at);
+    #[cfg(feature = "colored")]
+    {
+        let painted = crate::colors::paint("é", "bold red");
+        assert_eq!(painted.capacity(), painted.len());
+    }
+
+    //Mask::default and RenderMask::default_mask do not clash with both traits in scope
+    let _ = masks::Standard::default();
+    Ok(())
+}
+
+#[test]
+fn test_graphemes() -> Result<(), MaskError> {
+    //An accented letter typed as two characters, and an emoji with a skin tone
+    let mut buffer = EditBuffer::new();
+    for ch in "ae\u{301}\u{1f44d}\u{1f3fd}b".chars() {
+        buffer.insert(ch);
+    }
+    assert_eq!(buffer.len(), 4);
+    assert_eq!(buffer.cursor(), 4);
+
+    buffer.move_cursor(Motion::Left);
+    assert!(buffer.backspace());
+    assert_eq!(buffer.expose(), "ae\u{301}b");
+    buffer.move_cursor(Motion::Home);
+    buffer.move_cursor(Motion::Right);
+    assert!(buffer.delete());
+    assert_eq!(buffer.expose(), "ab");
+
+    //Wide echoed characters and wide masks are erased cell for cell
+    let masks: [(Box<dyn Mask>, &str); 3] = [
+        (Box::new(masks::Echo::default()), "\u{6587}"),
+        (Box::new(masks::Standard::new("\u{2605}\u{2605}")), "\u{2605}\u{2605}"),
+        (Box::new(masks::Standard::new("\u{732b}")), "\u{732b}"),
+    ];
+    for (mask, shown) in masks {
+        let (mut password, mut o) = (EditBuffer::new(), Vec::new());
+        for ch in "\u{4e2d}\u{6587}e\u{301}".chars() {
+            mask.feed_password(&mut password, ch, &mut o)?;
+        }
+        mask.move_cursor(&mut password, Motion::Home, &mut o)?;
+        mask.delete_password(&mut password, &mut o)?;
+        mask.move_cursor(&mut password, Motion::End, &mut o)?;
+        mask.pop_password(&mut password, &mut o)?;
+        assert_eq!(password.expose(), "\u{6587}");
+
+        assert_eq!(screen(&o), shown);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_scroll() -> Result<(), MaskError> {
+    use crate::render::{fit, Glyph, Line};
+
+    //Twenty characters in a row with room for ten: the end of the password stays in view
+    let mask = masks::Echo::default();
+    let (mut password, mut o) = (EditBuffer::new(), Vec::new());
+    password.set_view_width(Some(10));
+    for ch in "abcdefghijklmnopqrst".chars() {
+        mask.feed_password(&mut password, ch, &mut o)?;
+    }
+    assert_eq!(screen(&o), "<mnopqrst");
+
+    //Backspaces never go back past the start of the row
+    mask.move_cursor(&mut password, Motion::Home, &mut o)?;
+    mask.pop_password(&mut password, &mut o)?;
+    mask.delete_password(&mut password, &mut o)?;
+    assert_eq!(screen(&o), "bcdefghi>");
+
+    //The window follows the cursor, wide glyphs are never cut in half
+    let line = Line::new(vec![Glyph::new("\u{732b}", 2); 6], 3);
+    let fitted = fit(line, 6);
+    assert_eq!(fitted.width(), 6);
+    assert_eq!(fitted.cursor, 3);
+    assert_eq!(fitted.glyphs.first().map(Glyph::text), Some("<"));
+    Ok(())
+}
+
+#[test]
+fn test_mask_spec() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::render::RenderMask;
+    use crate::spec::MaskSpec;
+
+    let spec: MaskSpec = r##" Or("#, \"x\"", color = red) "##.parse()?;
+    assert!(spec.is(&["onereverse", "or"]));
+    assert_eq!(spec.positional(0).map(|a| a.value.as_str()), Some("#, \"x\""));
+    assert_eq!(spec.get("color").map(|a| (a.value.as_str(), a.position)), Some(("red", 16)));
+    assert_eq!(spec.to_string(), r##"Or("#, \"x\"", color="red")"##);
+
+    //The argument is no longer dropped, and the name has to match exactly
+    assert_eq!("standard(#)".parse::<masks::Standard>()?.to_string(), "standard(\"#\")");
+    assert_eq!("Standard[\\,]".parse::<masks::Standard>()?.to_string(), "standard(\",\")");
+    let error = |s: &str| s.parse::<masks::Standard>().err().map(|e| e.position);
+    assert_eq!(error("xstandardy"), Some(0));
+    assert_eq!(error("standard(#)y"), Some(11));
+    assert_eq!(error("standard(\"#)"), Some(9));
+    assert_eq!(error("standard(#, ##)"), Some(12));
+    assert_eq!(error("standard(size=2)"), Some(9));
+    assert_eq!(error("standard(\"\\q\")"), Some(10));
+
+    //Every mask displays to a spec that parses back to the same mask
+    let masks: Vec<Box<dyn std::fmt::Display>> = vec![
+        Box::new(masks::Standard::new("\u{2022}\"")),
+        Box::new(masks::Echo::default()),
+        Box::new(masks::Blind),
+        #[cfg(feature = "reverse")]
+        Box::new(reverse::MimiReverse::new("#")),
+        #[cfg(feature = "strength")]
+        Box::new("meter(width=4)".parse::<strength::StrengthMeter>()?),
+    ];
+    for mask in masks {
+        let spec = mask.to_string();
+        let reparsed = spec.parse::<MaskSpec>()?;
+        let again: String = match reparsed.name.as_str() {
+            "standard" => spec.parse::<masks::Standard>()?.to_string(),
+            "echo" => spec.parse::<masks::Echo>()?.to_string(),
+            "blind" => spec.parse::<masks::Blind>()?.to_string(),
+            #[cfg(feature = "reverse")]
+            "mimireverse" => spec.parse::<reverse::MimiReverse>()?.to_string(),
+            #[cfg(feature = "strength")]
+            "strength" => spec.parse::<strength::StrengthMeter>()?.to_string(),
+            name => panic!("unexpected mask {}", name),
+        };
+        assert_eq!(again, spec);
+    }
+
+    #[cfg(feature = "env")]
+    {
+        use crate::env::mask_from_str;
+        assert!(mask_from_str("mr(#)", None).is_ok() == cfg!(feature = "reverse"));
+        assert_eq!(mask_from_str("shadow", None).err().map(|e| e.position), Some(0));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "env")]
+#[test]
+fn test_env_macros() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::env::*;
+    use crate::spec::SpecError;
+
+    //Every form in the docs and the README, against variables no other test touches
+    std::env::set_var("GPASS_TEST_MACRO_PROMPT", "From the env: ");
+    std::env::set_var("GPASS_TEST_MACRO_MASK", "standard(#)");
+    std::env::remove_var("GPASS_TEST_MACRO_UNSET");
+
+    let default_prompt = prompt_from_env(GPASS_DEFAULT_ENV_VAR, GPASS_DEFAULT_PROMPT);
+    assert_eq!(env_prompt!(), default_prompt);
+    assert_eq!(env_prompt!("GPASS_TEST_MACRO_PROMPT"), "From the env: ");
+    assert_eq!(env_prompt!("GPASS_TEST_MACRO_UNSET"), GPASS_DEFAULT_PROMPT);
+    assert_eq!(
+        env_prompt!("GPASS_TEST_MACRO_UNSET", "My Prompt"),
+        "My Prompt"
+    );
+    assert_eq!(
+        env_prompt!(default = "My Prompt"),
+        prompt_from_env(GPASS_DEFAULT_ENV_VAR, "My Prompt")
+    );
+
+    //What a mask shows for one character, to tell them apart
+    fn shown(mask: Result<Box<dyn Mask>, SpecError>) -> Result<String, Box<dyn std::error::Error>> {
+        let (mut password, mut o) = (EditBuffer::new(), Vec::new());
+        mask?.feed_password(&mut password, 'a', &mut o)?;
+        Ok(screen(&o))
+    }
+    let fallback = masks::DEFAULT_MASK_CHAR;
+
+    assert_eq!(
+        env_mask!().is_some(),
+        std::env::var_os("GPASS_MASK").is_some()
+    );
+    assert!(env_mask!("GPASS_TEST_MACRO_UNSET").is_none());
+    assert_eq!(
+        shown(env_mask!("GPASS_TEST_MACRO_MASK").ok_or("not set")?)?,
+        "#"
+    );
+    assert_eq!(shown(env_mask!(value = "standard(#)"))?, "#");
+    assert_eq!(
+        shown(env_mask!(
+            "GPASS_TEST_MACRO_MASK",
+            default = masks::Standard::default()
+        ))?,
+        "#"
+    );
+    assert_eq!(
+        shown(env_mask!(
+            "GPASS_TEST_MACRO_UNSET",
+            default = masks::Standard::default()
+        ))?,
+        fallback
+    );
+
+    //The README example
+    if std::env::var_os("GPASS_MASK").is_none() {
+        assert_eq!(
+            shown(env_mask!(default = masks::Standard::default()))?,
+            fallback
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_mask_registry() -> Result<(), Box<dyn std::error::Error>> {
+    let mut registry = MaskRegistry::with_defaults();
+    assert!(registry.get("STANDARD").is_some());
+
+    //A custom mask with an alias, one argument and one option
+    registry.register(
+        "stars",
+        &["s"],
+        "Stars, or another mask string",
+        Schema::new().arg("mask").option("twice"),
+        |spec| {
+            let mask = spec.positional(0).map_or("*", |arg| arg.value.as_str());
+            let mask = match spec.get("twice") {
+                Some(_) => mask.repeat(2),
+                None => mask.to_string(),
+            };
+            Ok(Box::new(masks::Standard::new(&mask)))
+        },
+    );
+
+    let mask = registry.resolve("S(#, twice=yes)")?;
+    let (mut password, mut o) = (EditBuffer::new(), Vec::new());
+    mask.feed_password(&mut password, 'a', &mut o)?;
+    assert_eq!(screen(&o), "##");
+
+    //The schema is checked before the factory runs
+    let error = |s: &str| registry.resolve(s).err().map(|e| e.position);
+    assert_eq!(error("stars(#, ##)"), Some(9));
+    assert_eq!(error("stars(color=red)"), Some(6));
+    assert_eq!(error("planets"), Some(0));
+
+    //Taking over a name replaces the earlier mask, in place
+    registry.register("standard", &["s"], "", Schema::new(), |_| Ok(Box::new(masks::Blind)));
+    let names: Vec<String> = registry.masks().map(|mask| mask.name.clone()).collect();
+    assert_eq!(names.first().map(String::as_str), Some("standard"));
+    assert_eq!(names.last().map(String::as_str), Some("stars"));
+    assert_eq!(registry.get("s").map(|mask| mask.name.as_str()), Some("standard"));
+    assert_eq!(
+        registry.get("stars").map(ToString::to_string).as_deref(),
+        Some("stars(mask, twice=...): Stars, or another mask string")
+    );
+
+    //env_mask! resolves through the global registry, so it picks up masks the application registers there
+    #[cfg(feature = "env")]
+    {
+        MaskRegistry::global()
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .register("envstars", &["es"], "", Schema::new().arg("mask"), |spec| {
+                let mask = spec.positional(0).map_or("+", |arg| arg.value.as_str());
+                Ok(Box::new(masks::Standard::new(&mask.repeat(3))))
+            });
+        std::env::set_var("GPASS_TEST_REGISTRY_MASK", "es(#)");
+
+        let shown = |mask: Box<dyn Mask>| -> Result<String, MaskError> {
+            let (mut password, mut o) = (EditBuffer::new(), Vec::new());
+            mask.feed_password(&mut password, 'a', &mut o)?;
+            Ok(screen(&o))
+        };
+        let mask = env_mask!("GPASS_TEST_REGISTRY_MASK").ok_or("not set")??;
+        assert_eq!(shown(mask)?, "###");
+        assert_eq!(shown(env_mask!(value = "envstars")?)?, "+++");
+        let mask = env_mask!("GPASS_TEST_REGISTRY_MASK", default = masks::Blind)?;
+        assert_eq!(shown(mask)?, "###");
+        assert_eq!(
+            env_mask!(value = "envstars(#, ##)").err().map(|e| e.position),
+            Some(12)
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_errors() {
+    use std::error::Error;
+
+    fn send_sync<T: Send + Sync + 'static>() {}
+    send_sync::<LibError>();
+    send_sync::<MaskError>();
+
+    //Mask failures keep their cause as a source
+    let gone = std::io::Error::new(std::io::ErrorKind::BrokenPipe, "gone");
+    let error = LibError::from(MaskError::from(gone));
+    assert_eq!(error.to_string(), "the mask could not update the password");
+    let source = error.source().expect("mask error");
+    assert_eq!(source.to_string(), "could not write the mask output");
+    assert_eq!(source.source().map(ToString::to_string).as_deref(), Some("gone"));
+
+    let error = match MaskRegistry::with_defaults().resolve("shadow") {
+        Err(e) => LibError::from(e),
+        Ok(_) => panic!("unknown mask resolved"),
+    };
+    assert_eq!(error.to_string(), "invalid mask");
+    assert_eq!(
+        error.source().map(ToString::to_string).as_deref(),
+        Some("unknown mask \"shadow\" at position 0")
+    );
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn test_config() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::config::{Config, Settings};
+
+    let config: Config = r#"
+        prompt = "Password: "
+        mask = "standard(#)"
+        idle_timeout = 5
+
+        [apps.backup]
+        prompt = "Backup key: "
+        confirm = true
+
+        [profiles.paranoid]
+        mask = "blind"
+        paste = "reject"
+        attempts = 5
+    "#
+    .parse()?;
+
+    //File defaults < app section < profile < explicit values
+    let settings = config.settings(Some("backup"), Some("paranoid"))?;
+    assert_eq!(settings.prompt.as_deref(), Some("Backup key: "));
+    assert_eq!(settings.mask.as_deref(), Some("blind"));
+    let explicit = Settings {
+        prompt: Some("Key: ".into()),
+        ..Default::default()
+    };
+    let settings = settings.layer(explicit);
+
+    let mut gpass = GPass::default();
+    settings.apply(&mut gpass)?;
+    assert_eq!(gpass.prompt, "Key: ");
+    assert_eq!(gpass.paste, PastePolicy::Reject(DEFAULT_PASTE_REJECT_MESSAGE.into()));
+    assert_eq!(gpass.confirm.map(|c| c.attempts), Some(5));
+    assert_eq!(gpass.timeout, Some(Timeout::idle(Duration::from_secs(5))));
+
+    //Apps without a section get the defaults, unknown profiles are an error
+    assert_eq!(config.settings(Some("other"), None)?.prompt.as_deref(), Some("Password: "));
+    assert!(config.settings(None, Some("relaxed")).is_err());
+    assert!("mask = 3".parse::<Config>().is_err());
+
+    //File errors keep the path, and the io or toml error as their source
+    let dir = std::env::temp_dir().join(format!("gpass-test-config-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let broken = dir.join("config.toml");
+    std::fs::write(&broken, "mask = 3")?;
+    let missing = dir.join("missing.toml");
+    let results = [Config::from_file(&broken), Config::from_file(&missing)];
+    std::fs::remove_dir_all(&dir)?;
+
+    match &results {
+        [Err(LibError::Config {
+            path: Some(first),
+            source: Some(parse),
+            ..
+        }), Err(LibError::Config {
+            path: Some(second),
+            source: Some(read),
+            ..
+        })] => {
+            assert_eq!((first, second), (&broken, &missing));
+            assert!(parse.downcast_ref::<toml::de::Error>().is_some());
+            let read = read
+                .downcast_ref::<std::io::Error>()
+                .map(std::io::Error::kind);
+            assert_eq!(read, Some(std::io::ErrorKind::NotFound));
+        }
+        results => panic!("unexpected results {:?}", results),
+    }
+    let timeout = Settings {
+        idle_timeout: Some(-1.0),
+        ..Default::default()
+    };
+    let error = timeout
+        .apply(&mut GPass::default())
+        .err()
+        .ok_or("negative timeout applied")?;
+    assert_eq!(
+        error.to_string(),
+        "invalid configuration: invalid idle_timeout: -1 seconds"
+    );
+    assert!(std::error::Error::source(&error).is_some());
+    Ok(())
+}
+
+#[cfg(feature = "colored")]
+#[test]
+fn test_colors() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::colors::{self, ColorChoice};
+    use colored::Color;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let orange = Color::TrueColor { r: 255, g: 136, b: 0 };
+    assert_eq!(colors::parse("Bright_Blue")?, Some(Color::BrightBlue));
+    assert_eq!(colors::parse("#ff8800")?, Some(orange));
+    assert_eq!(colors::parse("#f80")?, Some(Color::TrueColor { r: 255, g: 136, b: 0 }));
+    assert_eq!(colors::parse("rgb(255, 136, 0)")?, Some(orange));
+    assert_eq!(colors::parse("255,136,0")?, Some(orange));
+    assert_eq!(colors::parse("none")?, None);
+    assert!(colors::parse("#ff88").is_err());
+    assert!(colors::parse("rgb(256, 0, 0)").is_err());
+    assert!(colors::parse("mauve").is_err());
+    assert!(spec::parse::<masks::Echo>("echo(color=\"#ff8800\")").is_ok());
+
+    assert_eq!(colors::paint("ab", "red"), "\x1b[31mab\x1b[0m");
+    assert_eq!(colors::paint("ab", colors::NONE), "ab");
+    assert_eq!("never".parse::<ColorChoice>()?, ColorChoice::Never);
+    assert!(!ColorChoice::Never.enabled(true));
+    assert!(ColorChoice::Always.enabled(false));
+
+    //Captures what GPass writes, since get_password takes the output stream with it
+    #[derive(Clone, Default)]
+    struct Captured(Rc<RefCell<Vec<u8>>>);
+
+    impl std::io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    for (choice, escapes) in [(ColorChoice::Always, true), (ColorChoice::Never, false)] {
+        let output = Captured::default();
+        let mut gpass = GPass::new(
+            Some("Key"),
+            Box::new(file_input::IString::new("pw".to_string())),
+            Box::new(masks::Echo::default()),
+            Box::new(output.clone()),
+            false,
+        );
+        gpass.set_color_choice(choice);
+        gpass.get_password()?;
+        assert_eq!(output.0.borrow().contains(&b'\x1b'), escapes);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_theme() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::render::Glyph;
+
+    //The placeholder is drawn until something is typed, and again once the password is empty
+    let mask = masks::Echo::default();
+    let mut password = EditBuffer::new();
+    password.set_placeholder(Some(Glyph::new("(hidden)", 8)));
+    let mut o = Vec::new();
+    mask.redraw_password(&password, &mut o)?;
+    assert_eq!(screen(&o), "(hidden)");
+    mask.feed_password(&mut password, 'a', &mut o)?;
+    assert_eq!(screen(&o), "a");
+    mask.pop_password(&mut password, &mut o)?;
+    assert_eq!(screen(&o), "(hidden)");
+    mask.end_password(&password, &mut o)?;
+    assert_eq!(screen(&o), "");
+
+    #[cfg(feature = "colored")]
+    {
+        use crate::colors::{self, Style, Theme};
+        use crate::render::RenderMask;
+
+        let style: Style = "bold italic bright blue".parse()?;
+        assert!(style.bold && style.italic && !style.dim);
+        assert_eq!(style.color, Some(colored::Color::BrightBlue));
+        assert!("bold mauve".parse::<Style>().is_err());
+        assert_eq!(colors::paint("ab", "bold red"), "\x1b[1;31mab\x1b[0m");
+        assert_eq!(colors::paint("ab", "dim"), "\x1b[2mab\x1b[0m");
+
+        for name in Theme::NAMES {
+            assert!(name.parse::<Theme>().is_ok());
+        }
+        assert!("neon".parse::<Theme>().is_err());
+        let mono = Theme::named("mono").unwrap_or_default();
+
+        //The revealed character takes its own style
+        #[cfg(feature = "reverse")]
+        {
+            let mut mask = reverse::OneReverse::default();
+            RenderMask::set_theme(&mut mask, &mono);
+            let mut password = EditBuffer::new();
+            let mut o = Vec::new();
+            mask.feed_password(&mut password, 'a', &mut o)?;
+            assert!(String::from_utf8(o)?.contains("\x1b[1ma\x1b[0m"));
+            assert!(mask.to_string().contains("reveal=\"bold\""));
+        }
+
+        let mut gpass = GPass::default();
+        gpass.set_theme(mono.clone());
+        gpass.set_mask_color("red");
+        assert_eq!(gpass.theme.prompt, "bold");
+        assert_eq!(gpass.theme.mask, "red");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_reader_input() -> Result<(), Box<dyn std::error::Error>> {
+    //Hands out one byte per read, like a slow pipe splitting characters across reads
+    struct Trickle<'a>(&'a [u8]);
+
+    impl std::io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.0.split_first() {
+                Some((byte, rest)) if !buf.is_empty() => {
+                    buf[0] = *byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    //Each line answers one prompt, `\r\n` counts as a single newline
+    let input = ReaderInput::new(Trickle("pässwörd\r\npässwörd\nrest".as_bytes()));
+    let mut gpass = GPass::new(None, Box::new(input), Box::new(masks::Blind), Box::new(Void), true);
+    gpass.set_confirm(Confirm::default());
+    assert_eq!(gpass.get_password()?.expose(), "pässwörd");
+
+    let mut input = ReaderInput::with_capacity(&b"a\nb"[..], 2);
+    input.set_end_on_newline(false);
+    let tokens = std::iter::from_fn(|| match input.get_token() {
+        Ok(InputToken::EOF) | Err(_) => None,
+        token => token.ok(),
+    });
+    assert_eq!(tokens.count(), 3);
+
+    let mut input = ReaderInput::new(&b"\xe4\xb8"[..]);
+    assert!(matches!(input.get_token(), Err(LibError::InvalidCharacter(_))));
+    Ok(())
+}
+
+#[test]
+fn test_encodings() -> Result<(), Box<dyn std::error::Error>> {
+    //Reads the whole input, newlines included
+    fn decode(bytes: &[u8], encoding: Encoding, mode: DecodeMode) -> Result<String, LibError> {
+        let mut input = ReaderInput::new(bytes);
+        input.set_encoding(encoding);
+        input.set_decode_mode(mode);
+        input.set_end_on_newline(false);
+
+        let mut text = String::new();
+        loop {
+            match input.get_token()? {
+                InputToken::Character(ch) => text.push(ch),
+                _ => return Ok(text),
+            }
+        }
+    }
+    let (utf8, strict, lossy) = (Encoding::Utf8, DecodeMode::Strict, DecodeMode::Lossy);
+
+    assert_eq!(decode("pä😀".as_bytes(), utf8, strict)?, "pä😀");
+    //Byte order marks are skipped and pick the encoding
+    assert_eq!(decode(b"\xef\xbb\xbfp\xc3\xa4", utf8, strict)?, "pä");
+    assert_eq!(decode(b"\xff\xfep\x00\xe4\x00", utf8, strict)?, "pä");
+    assert_eq!(decode(b"\xef\xbbp", utf8, lossy)?, "\u{fffd}p");
+    assert_eq!(decode(b"\x00p\xd8\x3d\xde\x00", Encoding::Utf16Be, strict)?, "p😀");
+    assert_eq!(decode(b"caf\xe9", Encoding::Latin1, strict)?, "café");
+    assert_eq!("UTF-16LE".parse::<Encoding>()?, Encoding::Utf16Le);
+
+    //Malformed input is an error, or replaced when lossy
+    assert!(matches!(decode(b"a\xffb", utf8, strict), Err(LibError::InvalidCharacter(_))));
+    assert_eq!(decode(b"a\xffb", utf8, lossy)?, "a\u{fffd}b");
+    assert_eq!(decode(b"\xc3A\xe0\x80\x80", utf8, lossy)?, "\u{fffd}A\u{fffd}");
+    assert_eq!(decode(b"\x3d\xd8p\x00", Encoding::Utf16Le, lossy)?, "\u{fffd}p");
+    assert!(decode(b"p\x00q", Encoding::Utf16Le, strict).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_script() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::script::{Recorder, Script, Step};
+
+    let gpass = |script: &str, ctrl_c_abort: bool| -> Result<GPass, Box<dyn std::error::Error>> {
+        let script: Script = script.parse()?;
+        Ok(GPass::new(None, Box::new(script), Box::new(masks::Echo::default()), Box::new(Void), ctrl_c_abort))
+    };
+
+    assert_eq!(gpass("hel<BS>lo<Left>X<Enter>ignored", true)?.get_password()?.expose(), "helXo");
+    //Ctrl-C either aborts or ends the input with what was typed
+    assert!(matches!(gpass("abc<C-c>", true)?.get_password(), Err(LibError::UserInterrupt)));
+    assert_eq!(gpass("abc<C-c>", false)?.get_password()?.expose(), "abc");
+
+    //Scripts write back to the same script
+    let text = r"a\<b<Paste:x\>y><Wait:5ms><A-f><F3><C-Left><Redraw><C-c><Error:boom>";
+    let script: Script = text.parse()?;
+    assert_eq!(script.len(), 10);
+    assert!(matches!(script.steps().nth(4), Some((delay, Step::Token(InputToken::Alt('f')))) if delay.as_millis() == 5));
+    assert_eq!(script.to_string(), text);
+    assert_eq!("<Nope>x".parse::<Script>().unwrap_err().position, 0);
+    assert_eq!("ab<BS".parse::<Script>().unwrap_err().position, 2);
+    assert!("<Wait:soon>".parse::<Script>().is_err());
+
+    //A delay longer than the idle timeout times out
+    let script = Script::new().text("a").delay(Duration::from_millis(200)).text("b");
+    let mut gpass = GPass::new(None, Box::new(script), Box::new(masks::Blind), Box::new(Void), true);
+    gpass.set_timeout(Timeout::idle(Duration::from_millis(20)));
+    assert!(matches!(gpass.get_password(), Err(LibError::Timeout)));
+
+    //A recorded session replays to the same password
+    let mut recorder = Recorder::new("pw<Left>a<Enter>".parse::<Script>()?);
+    recorder.set_timed(false);
+    let recording = recorder.recording();
+    GPass::new(None, Box::new(recorder), Box::new(masks::Blind), Box::new(Void), true).get_password()?;
+    assert_eq!(recording.script().to_string(), "pw<Left>a<Enter>");
+    let replay = GPass::new(None, Box::new(recording.script()), Box::new(masks::Blind), Box::new(Void), true);
+    assert_eq!(replay.get_password()?.expose(), "paw");
+    Ok(())
+}
+
+#[cfg(feature = "reverse")]
+#[test]
+fn test_screen() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::{script::Script, testing};
+    use std::io::Write;
+
+    //The visible line after every token
+    let gpass = |mask: Box<dyn Mask>| GPass::new(Some("Key"), Box::new(Script::new()), mask, Box::new(Void), true);
+    let run = testing::run(gpass(Box::new(reverse::OneReverse::default())), "ab<BS>c<Enter>".parse()?);
+    assert_eq!(run.result?.expose(), "ac");
+    assert_eq!(run.frames, ["Key:", "Key: a", "Key: *b", "Key: *", "Key: *c", ""]);
+    assert_eq!(run.screen.lines(), ["Key: **", ""]);
+
+    //MimiReverse shows the character before the one erased
+    let run = testing::run(gpass(Box::new(reverse::MimiReverse::default())), "ab<BS>c<Enter>".parse()?);
+    assert_eq!(run.frames, ["Key:", "Key: a", "Key: *b", "Key: a", "Key: *c", ""]);
+
+    //Redraws, styles and wide characters
+    let mut screen = testing::Screen::new();
+    write!(screen, "pass: abc\r\x1b[Kpass: \x1b[1;32mx\x1b[0m\u{8}y界")?;
+    assert_eq!(screen.line(), "pass: y界");
+    assert_eq!(screen.cursor(), (0, 9));
+    assert_eq!(screen.cell(0, 6).map(|cell| cell.sgr), Some(String::new()));
+    screen.write_all(b"\x1b[9D\x1b[31mZ\xe2")?;
+    screen.write_all(b"\x82\xac\x1b[0m\n")?;
+    assert_eq!(screen.row(0), "Z€ss: y界");
+    assert_eq!(screen.cell(0, 0).map(|cell| cell.sgr).as_deref(), Some("31"));
+    assert_eq!(screen.cursor(), (1, 0));
+    Ok(())
+}
+
+#[cfg(all(target_os = "linux", feature = "pty"))]
+#[test]
+fn test_pty_getch() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::pty::{self, Pty};
+
+    if pty::is_child() {
+        pty::report(&GPass::default().get_password());
+        return Ok(());
+    }
+
+    //Typing, editing keys sent as escape sequences, a bracketed paste with its newline, and a resize
+    let mut pty = Pty::test("tests::test_pty_getch")?;
+    pty.expect("Enter the Password")?;
+    assert!(pty.is_raw()?);
+    assert!(pty.screen().bracketed_paste());
+    pty.send("hel\x7flo\x1b[DX")?;
+    pty.expect_line("Enter the Password: *****")?;
+    pty.set_size(24, 40)?;
+    pty.send("\x1b[200~!\n\x1b[201~\r")?;
+    assert_eq!(pty.result()?, r#"Ok("helX!o")"#);
+    assert!(pty.wait()?.success());
+
+    //The terminal is back the way it was
+    assert!(!pty.is_raw()?);
+    assert!(!pty.screen().bracketed_paste());
+    Ok(())
+}
+
+#[cfg(all(target_os = "linux", feature = "pty"))]
+#[test]
+fn test_pty_interrupt() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::pty::{self, Pty};
+    use std::os::unix::process::ExitStatusExt;
+
+    if pty::is_child() {
+        pty::report(&GPass::default().get_password());
+        return Ok(());
+    }
+
+    //Ctrl-C raises SIGINT rather than being read, the terminal is restored before the process dies of it
+    let mut pty = Pty::test("tests::test_pty_interrupt")?;
+    pty.expect("Enter the Password")?;
+    pty.send("ab")?;
+    pty.expect_line("Enter the Password: **")?;
+    pty.send("\x03")?;
+    assert_eq!(pty.wait()?.signal(), Some(libc::SIGINT));
+    assert!(!pty.output().contains(pty::RESULT_PREFIX));
+    assert!(!pty.is_raw()?);
+    assert!(!pty.screen().bracketed_paste());
+    Ok(())
+}
+
+#[cfg(all(target_os = "linux", feature = "pty"))]
+#[test]
+fn test_pty_handled_interrupt() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::pty::{self, Pty};
+
+    //An application handler that returns instead of exiting, like ctrlc or tokio::signal
+    extern "C" fn handled(_signal: libc::c_int) {
+        let message = b"handled\n";
+        unsafe { libc::write(libc::STDOUT_FILENO, message.as_ptr() as *const libc::c_void, message.len()) };
+    }
+
+    if pty::is_child() {
+        unsafe { libc::signal(libc::SIGINT, handled as extern "C" fn(libc::c_int) as libc::sighandler_t) };
+        pty::report(&GPass::default().get_password());
+        return Ok(());
+    }
+
+    //The prompt carries on in raw mode, so nothing typed after the signal is echoed
+    let mut pty = Pty::test("tests::test_pty_handled_interrupt")?;
+    pty.expect("Enter the Password")?;
+    pty.send("ab")?;
+    pty.expect_line("Enter the Password: **")?;
+    pty.send("\x03")?;
+    pty.expect("handled")?;
+    //Written when the terminal is set up again
+    pty.expect("\x1b[?2004h")?;
+    assert!(pty.is_raw()?);
+    pty.send("cd")?;
+    pty.expect_line("Enter the Password: ****")?;
+    pty.send("\r")?;
+    assert_eq!(pty.result()?, r#"Ok("abcd")"#);
+
+    let output = pty.output();
+    let typed = output.find("handled").unwrap_or(0)..output.find(pty::RESULT_PREFIX).unwrap_or(output.len());
+    assert!(!output[typed].contains("cd"));
+    assert!(pty.wait()?.success());
+    Ok(())
+}
+
+#[cfg(all(target_os = "linux", feature = "pty"))]
+#[test]
+fn test_pty_stop() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::pty::{self, Pty};
+    use std::time::{Duration, Instant};
+
+    if pty::is_child() {
+        pty::report(&GPass::default().get_password());
+        return Ok(());
+    }
+
+    //Ctrl-Z hands the terminal back while the prompt is stopped
+    let mut pty = Pty::test_job("tests::test_pty_stop")?;
+    pty.expect("Enter the Password")?;
+    pty.send("ab")?;
+    pty.expect_line("Enter the Password: **")?;
+    pty.send("\x1a")?;
+    pty.expect("\x1b[?2004l")?;
+    let deadline = Instant::now() + pty::DEFAULT_TIMEOUT;
+    while !pty.is_stopped()? {
+        assert!(Instant::now() < deadline, "the prompt did not stop");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert!(pty.termios()?.c_lflag & libc::ECHO != 0);
+    assert!(!pty.is_raw()?);
+    assert!(!pty.screen().bracketed_paste());
+
+    //Resumed as by fg, the terminal is set up again and the prompt drawn anew
+    pty.signal(libc::SIGCONT)?;
+    pty.expect("\x1b[?2004h")?;
+    assert!(pty.is_raw()?);
+    pty.send("c")?;
+    pty.expect("Enter the Password")?;
+    pty.expect_line("Enter the Password: ***")?;
+    pty.send("\r")?;
+    assert_eq!(pty.result()?, r#"Ok("abc")"#);
+    assert!(pty.wait()?.success());
+    Ok(())
+}
+
+#[cfg(all(target_os = "linux", feature = "pty"))]
+#[test]
+fn test_pty_panic() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::pty::{self, Pty};
+
+    //A mask that breaks on the first key, while the terminal is held
+    struct Broken;
+    impl Mask for Broken {
+        fn default() -> Self {
+            Broken
+        }
+        fn feed_password(
+            &self,
+            _: &mut EditBuffer,
+            _: char,
+            _: &mut dyn std::io::Write,
+        ) -> Result<(), MaskError> {
+            panic!("broken mask");
+        }
+        fn pop_password(
+            &self,
+            _: &mut EditBuffer,
+            _: &mut dyn std::io::Write,
+        ) -> Result<(), MaskError> {
+            Ok(())
+        }
+        fn delete_password(
+            &self,
+            _: &mut EditBuffer,
+            _: &mut dyn std::io::Write,
+        ) -> Result<(), MaskError> {
+            Ok(())
+        }
+        fn move_cursor(
+            &self,
+            _: &mut EditBuffer,
+            _: Motion,
+            _: &mut dyn std::io::Write,
+        ) -> Result<(), MaskError> {
+            Ok(())
+        }
+        #[cfg(feature = "colored")]
+        fn set_color(&mut self, _: &str) {}
+    }
+
+    if pty::is_child() {
+        //Runs as the panic message would, after GPass's hook has restored the terminal
+        std::panic::set_hook(Box::new(|_| {
+            let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+            unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut termios) };
+            println!(
+                "echo while panicking: {}",
+                termios.c_lflag & libc::ECHO != 0
+            );
+        }));
+        let gpass = GPass {
+            mask: Box::new(Broken),
+            ..Default::default()
+        };
+        pty::report(&gpass.get_password());
+        return Ok(());
+    }
+
+    let mut pty = Pty::test("tests::test_pty_panic")?;
+    pty.expect("Enter the Password")?;
+    assert!(pty.is_raw()?);
+    pty.send("a")?;
+    pty.expect("echo while panicking: true")?;
+    assert!(!pty.wait()?.success());
+    assert!(!pty.output().contains(pty::RESULT_PREFIX));
+    assert!(!pty.is_raw()?);
+    assert!(!pty.screen().bracketed_paste());
+    Ok(())
+}
+
+#[cfg(all(target_os = "linux", feature = "pty", feature = "tokio"))]
+#[test]
+fn test_pty_async_redraw() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::pty::{self, Pty};
+    use std::time::{Duration, Instant};
+
+    if pty::is_child() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .enable_time()
+            .build()?;
+        let result = runtime.block_on(async {
+            let input = crate::async_input::TokioInput::stdin()?;
+            GPass::default().get_password_async(input).await
+        });
+        pty::report(&result);
+        return Ok(());
+    }
+
+    //The prompt is drawn again while it waits for a key, nothing has to be typed first
+    let mut pty = Pty::test_job("tests::test_pty_async_redraw")?;
+    pty.expect("Enter the Password")?;
+    pty.send("ab")?;
+    pty.expect_line("Enter the Password: **")?;
+    pty.set_size(24, 40)?;
+    pty.expect("Enter the Password")?;
+    pty.expect_line("Enter the Password: **")?;
+
+    pty.send("\x1a")?;
+    pty.expect("\x1b[?2004l")?;
+    let deadline = Instant::now() + pty::DEFAULT_TIMEOUT;
+    while !pty.is_stopped()? {
+        assert!(Instant::now() < deadline, "the prompt did not stop");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    pty.signal(libc::SIGCONT)?;
+    pty.expect("Enter the Password")?;
+    pty.expect_line("Enter the Password: **")?;
+    assert!(pty.is_raw()?);
+
+    pty.send("c\r")?;
+    assert_eq!(pty.result()?, r#"Ok("abc")"#);
+    assert!(pty.wait()?.success());
     Ok(())
 }
@@ -0,0 +1,46 @@
+//!Timeouts for the password prompt<br>
+//!An idle timeout gives up when no key has been pressed for a while, a total timeout when the whole prompt takes too long<br>
+//!On timeout, get_password returns LibError::Timeout<br>
+
+use std::time::{Duration, Instant};
+
+///Limits on how long get_password waits for input<br>
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timeout {
+    ///Longest wait between two keystrokes<br>
+    pub idle: Option<Duration>,
+
+    ///Longest wait for the whole prompt, including confirmation and policy retries<br>
+    pub total: Option<Duration>,
+}
+
+impl Timeout {
+    pub fn idle(idle: Duration) -> Self {
+        Self {
+            idle: Some(idle),
+            total: None,
+        }
+    }
+
+    pub fn total(total: Duration) -> Self {
+        Self {
+            idle: None,
+            total: Some(total),
+        }
+    }
+
+    ///Time left to wait for the next keystroke, None if there is no limit<br>
+    pub fn remaining(&self, started: Instant, last_input: Instant) -> Option<Duration> {
+        let idle = self
+            .idle
+            .map(|idle| idle.saturating_sub(last_input.elapsed()));
+        let total = self
+            .total
+            .map(|total| total.saturating_sub(started.elapsed()));
+
+        match (idle, total) {
+            (Some(idle), Some(total)) => Some(idle.min(total)),
+            (idle, total) => idle.or(total),
+        }
+    }
+}